@@ -0,0 +1,24 @@
+#ifndef MY_HASHMAP_H
+#define MY_HASHMAP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define MAP_OK 0
+#define MAP_OMEM -1
+#define MAP_FULL -2
+#define MAP_MISSING -3
+#define MAP_NULL -4
+
+/* Opaque map with int32_t keys and values. */
+typedef struct my_hashmap my_hashmap;
+
+my_hashmap *my_hashmap_new(void);
+int32_t my_hashmap_put(my_hashmap *m, int32_t key, int32_t value);
+/* Returns MAP_OK and fills *out on a hit, a nonzero status otherwise. */
+int32_t my_hashmap_get(const my_hashmap *m, int32_t key, int32_t *out);
+int32_t my_hashmap_remove(my_hashmap *m, int32_t key);
+size_t my_hashmap_length(const my_hashmap *m);
+void my_hashmap_free(my_hashmap *m);
+
+#endif
@@ -0,0 +1,14 @@
+//! Compiles only if `my_hashmap` and everything it pulls in build from `core`
+//! and `alloc` alone. See Cargo.toml for the command.
+
+#![no_std]
+
+use my_hashmap::{hashmap_get_ref, hashmap_new, hashmap_put, hashmap_remove, MapError};
+
+pub fn round_trip(key: u32, value: u32) -> Result<u32, MapError> {
+    let mut m = hashmap_new::<u32, u32>();
+    hashmap_put(&mut m, key, value)?;
+    let stored = hashmap_get_ref(&m, &key).copied();
+    hashmap_remove(&mut m, &key)?;
+    stored.ok_or(MapError::Missing)
+}
@@ -0,0 +1,74 @@
+use core::hash::{BuildHasher, Hash};
+
+use crate::{
+    hashmap_contains_key, hashmap_find, hashmap_first_occupied, hashmap_get_ref, hashmap_length,
+    hashmap_put, hashmap_remove, hashmap_remove_at, hashmap_with_capacity, DefaultHashBuilder,
+    MapError, MapT,
+};
+
+/// A map that holds at most `max_entries` entries and never rehashes.
+///
+/// The table is sized for `max_entries` up front. Inserting a new key into
+/// a full map first evicts an existing entry. The victim is arbitrary: it is
+/// currently the first occupied slot in table order, not the oldest or least
+/// recently used entry.
+pub struct BoundedHashMap<K, V, S = DefaultHashBuilder> {
+    inner: MapT<K, V, S>,
+    max_entries: usize,
+}
+
+impl<K, V> BoundedHashMap<K, V> {
+    pub fn new(max_entries: usize) -> Self {
+        BoundedHashMap {
+            inner: hashmap_with_capacity(max_entries),
+            max_entries,
+        }
+    }
+}
+
+impl<K, V, S> BoundedHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Inserts or overwrites `key`. If the map was full and `key` is new,
+    /// returns the entry that was evicted to make room.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<(K, V)>, MapError> {
+        let mut evicted = None;
+        if hashmap_find(&self.inner, &key).is_none() && self.len() >= self.max_entries {
+            let index = hashmap_first_occupied(&self.inner).ok_or(MapError::Full)?;
+            let (key, value, _) = hashmap_remove_at(&mut self.inner, index);
+            evicted = Some((key, value));
+        }
+        hashmap_put(&mut self.inner, key, value)?;
+        Ok(evicted)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        hashmap_get_ref(&self.inner, key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        hashmap_contains_key(&self.inner, key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<V, MapError> {
+        hashmap_remove(&mut self.inner, key)
+    }
+
+    pub fn len(&self) -> usize {
+        hashmap_length(&self.inner)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    pub fn into_inner(self) -> MapT<K, V, S> {
+        self.inner
+    }
+}
@@ -0,0 +1,78 @@
+use std::hash::{BuildHasher, Hash};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    hashmap_contains_key, hashmap_get_ref, hashmap_new, hashmap_put, hashmap_remove,
+    DefaultHashBuilder, MapError, MapT,
+};
+
+/// A map that can be shared between threads.
+///
+/// Every operation takes a single lock over the whole table: readers run
+/// in parallel, but any insert or remove blocks everyone else. That keeps
+/// it simple and safe, at the cost of write throughput under contention.
+pub struct ConcurrentHashMap<K, V, S = DefaultHashBuilder> {
+    inner: RwLock<MapT<K, V, S>>,
+}
+
+impl<K, V> ConcurrentHashMap<K, V> {
+    pub fn new() -> Self {
+        ConcurrentHashMap {
+            inner: RwLock::new(hashmap_new()),
+        }
+    }
+}
+
+impl<K, V> Default for ConcurrentHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> From<MapT<K, V, S>> for ConcurrentHashMap<K, V, S> {
+    fn from(m: MapT<K, V, S>) -> Self {
+        ConcurrentHashMap {
+            inner: RwLock::new(m),
+        }
+    }
+}
+
+impl<K, V, S> ConcurrentHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn read(&self) -> RwLockReadGuard<'_, MapT<K, V, S>> {
+        self.inner.read().expect("ConcurrentHashMap lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MapT<K, V, S>> {
+        self.inner.write().expect("ConcurrentHashMap lock poisoned")
+    }
+
+    /// Returns a copy of the value, since a reference cannot outlive the lock.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        hashmap_get_ref(&self.read(), key).cloned()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        hashmap_contains_key(&self.read(), key)
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Result<Option<V>, MapError> {
+        hashmap_put(&mut self.write(), key, value)
+    }
+
+    pub fn remove(&self, key: &K) -> Result<V, MapError> {
+        hashmap_remove(&mut self.write(), key)
+    }
+
+    pub fn into_inner(self) -> MapT<K, V, S> {
+        self.inner
+            .into_inner()
+            .expect("ConcurrentHashMap lock poisoned")
+    }
+}
@@ -0,0 +1,104 @@
+//! C bindings for a map with `int32_t` keys and values.
+//!
+//! Build with `cargo rustc --lib --release --features ffi --crate-type cdylib` (or
+//! `staticlib`) and include `include/my_hashmap.h`.
+
+use alloc::boxed::Box;
+use core::ptr;
+
+use crate::{
+    hashmap_get_ref, hashmap_length, hashmap_new, hashmap_put, hashmap_remove, MapError, MapT,
+};
+
+pub const MAP_OK: i32 = 0;
+pub const MAP_OMEM: i32 = -1;
+pub const MAP_FULL: i32 = -2;
+pub const MAP_MISSING: i32 = -3;
+pub const MAP_NULL: i32 = -4;
+
+pub type FfiMap = MapT<i32, i32>;
+
+fn status(result: Result<(), MapError>) -> i32 {
+    match result {
+        Ok(()) => MAP_OK,
+        Err(MapError::OutOfMemory) => MAP_OMEM,
+        Err(MapError::Missing) => MAP_MISSING,
+        Err(_) => MAP_FULL,
+    }
+}
+
+/// Allocates an empty map. Release it with `my_hashmap_free`.
+#[no_mangle]
+pub extern "C" fn my_hashmap_new() -> *mut FfiMap {
+    Box::into_raw(Box::new(hashmap_new()))
+}
+
+/// # Safety
+///
+/// `m` must be null or a pointer returned by `my_hashmap_new` that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn my_hashmap_put(m: *mut FfiMap, key: i32, value: i32) -> i32 {
+    match m.as_mut() {
+        Some(m) => status(hashmap_put(m, key, value).map(|_| ())),
+        None => MAP_NULL,
+    }
+}
+
+/// Writes the value stored under `key` to `*out` and returns `MAP_OK`, or
+/// returns a nonzero status and leaves `*out` untouched.
+///
+/// # Safety
+///
+/// `m` must be null or a live pointer from `my_hashmap_new`; `out` must be
+/// null or valid for a write of one `int32_t`.
+#[no_mangle]
+pub unsafe extern "C" fn my_hashmap_get(m: *const FfiMap, key: i32, out: *mut i32) -> i32 {
+    let m = match m.as_ref() {
+        Some(m) => m,
+        None => return MAP_NULL,
+    };
+    if out.is_null() {
+        return MAP_NULL;
+    }
+    match hashmap_get_ref(m, &key) {
+        Some(value) => {
+            ptr::write(out, *value);
+            MAP_OK
+        }
+        None => MAP_MISSING,
+    }
+}
+
+/// # Safety
+///
+/// `m` must be null or a live pointer from `my_hashmap_new`.
+#[no_mangle]
+pub unsafe extern "C" fn my_hashmap_remove(m: *mut FfiMap, key: i32) -> i32 {
+    match m.as_mut() {
+        Some(m) => status(hashmap_remove(m, &key).map(|_| ())),
+        None => MAP_NULL,
+    }
+}
+
+/// # Safety
+///
+/// `m` must be null or a live pointer from `my_hashmap_new`.
+#[no_mangle]
+pub unsafe extern "C" fn my_hashmap_length(m: *const FfiMap) -> usize {
+    match m.as_ref() {
+        Some(m) => hashmap_length(m),
+        None => 0,
+    }
+}
+
+/// # Safety
+///
+/// `m` must be null or a pointer from `my_hashmap_new`; it must not be used
+/// again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn my_hashmap_free(m: *mut FfiMap) {
+    if !m.is_null() {
+        drop(Box::from_raw(m));
+    }
+}
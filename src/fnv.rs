@@ -0,0 +1,53 @@
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// 64-bit FNV-1a. It is fast and needs neither `std` nor a source of
+/// randomness, but it is unseeded, so keys chosen by an attacker can be
+/// made to collide. It is the default hasher when the `std` feature is off.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+
+/// Builds FNV-1a hashers whose state starts from a fixed seed. Two maps with
+/// the same seed and the same insertion sequence iterate in the same order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeededFnvBuildHasher {
+    seed: u64,
+}
+
+impl SeededFnvBuildHasher {
+    pub fn new(seed: u64) -> Self {
+        SeededFnvBuildHasher { seed }
+    }
+}
+
+impl BuildHasher for SeededFnvBuildHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        let mut hasher = FnvHasher::default();
+        hasher.write(&self.seed.to_le_bytes());
+        hasher
+    }
+}
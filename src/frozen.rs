@@ -0,0 +1,62 @@
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+
+use crate::{
+    hashmap_contains_key, hashmap_get_ref, hashmap_iter, hashmap_length, DefaultHashBuilder, Iter,
+    MapT,
+};
+
+/// A map that can no longer be changed, see `hashmap_freeze`.
+///
+/// Only lookups and iteration are exposed, so no insert or remove can slip
+/// in once the map is built. It wraps the map as-is; `thaw` hands it back.
+#[derive(Clone, Debug)]
+pub struct FrozenHashMap<K, V, S = DefaultHashBuilder> {
+    inner: MapT<K, V, S>,
+}
+
+impl<K, V, S> From<MapT<K, V, S>> for FrozenHashMap<K, V, S> {
+    fn from(m: MapT<K, V, S>) -> Self {
+        FrozenHashMap { inner: m }
+    }
+}
+
+impl<K, V, S> FrozenHashMap<K, V, S> {
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        hashmap_iter(&self.inner)
+    }
+
+    pub fn len(&self) -> usize {
+        hashmap_length(&self.inner)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the map, mutable again.
+    pub fn thaw(self) -> MapT<K, V, S> {
+        self.inner
+    }
+}
+
+impl<K, V, S> FrozenHashMap<K, V, S>
+where
+    S: BuildHasher,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        hashmap_get_ref(&self.inner, key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        hashmap_contains_key(&self.inner, key)
+    }
+}
@@ -0,0 +1,3064 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::iter::FromIterator;
+use core::mem;
+use core::ops::{Add, Index};
+use core::slice;
+
+mod bounded;
+#[cfg(feature = "std")]
+mod concurrent;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fnv;
+mod frozen;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "stats")]
+mod stats;
+
+pub use bounded::BoundedHashMap;
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentHashMap;
+pub use fnv::{FnvBuildHasher, FnvHasher, SeededFnvBuildHasher};
+pub use frozen::FrozenHashMap;
+#[cfg(feature = "stats")]
+pub use stats::MapStats;
+
+/// Hasher used by `hashmap_new` and friends: std's randomly seeded SipHash
+/// when the `std` feature is on, FNV-1a otherwise.
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = FnvBuildHasher;
+
+// Maps from `hashmap_new` and friends start without a table and allocate
+// this many slots on their first insert.
+const INIT_CAP: usize = 1024;
+pub const MIN_CAP: usize = 16;
+const DEFAULT_MAX_LOAD_FACTOR: f64 = 0.7;
+const DEFAULT_GROWTH_FACTOR: f64 = 2.0;
+// With auto-shrink on, a removal that leaves the map below this load factor
+// shrinks the table. It sits well under half the default max load factor,
+// so a freshly shrunk table is never close to growing again.
+const AUTO_SHRINK_LOAD_FACTOR: f64 = 0.15;
+
+// Slot states kept in HashMapElement::in_use. Whether a slot is live is
+// decided by this flag alone, never by the key it holds.
+const SLOT_EMPTY: i32 = 0;
+const SLOT_IN_USE: i32 = 1;
+// Left behind by removals under quadratic probing, where entries cannot be
+// shifted back. Lookups step over it; inserts may reuse it.
+const SLOT_DELETED: i32 = 2;
+
+/// How a lookup steps through the table after its ideal bucket is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeStrategy {
+    /// Try the next slot each time. Removals shift entries back.
+    Linear,
+    /// Step by 1, 2, 3, ... slots, so the offsets from the ideal bucket are
+    /// the triangular numbers. That reaches every slot only because table
+    /// sizes are always powers of two. Removals leave tombstones.
+    Quadratic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    Full,
+    Missing,
+    OutOfMemory,
+    InvalidLoadFactor,
+    InvalidGrowthFactor,
+    WeightOverflow,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Full => write!(f, "hashmap is full"),
+            MapError::Missing => write!(f, "key is not in the hashmap"),
+            MapError::OutOfMemory => write!(f, "hashmap ran out of memory"),
+            MapError::InvalidLoadFactor => write!(f, "load factor must be between 0 and 1"),
+            MapError::InvalidGrowthFactor => write!(f, "growth factor must be greater than 1"),
+            MapError::WeightOverflow => write!(f, "total weight would overflow a u64"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MapError {}
+
+pub type MapT<K, V, S = DefaultHashBuilder> = HashMapMap<K, V, S>;
+
+struct HashMapElement<K, V> {
+    key: Option<K>,
+    in_use: i32,
+    // Set exactly when `key` is, so empty slots need no placeholder value.
+    data: Option<V>,
+    // Caller-assigned size of the entry, see `hashmap_put_weighted`.
+    weight: u64,
+}
+
+impl<K: Clone, V: Clone> Clone for HashMapElement<K, V> {
+    fn clone(&self) -> Self {
+        HashMapElement {
+            key: self.key.clone(),
+            in_use: self.in_use,
+            data: self.data.clone(),
+            weight: self.weight,
+        }
+    }
+
+    // Forwarded so keys and values can reuse their own buffers.
+    fn clone_from(&mut self, source: &Self) {
+        self.key.clone_from(&source.key);
+        self.in_use = source.in_use;
+        self.data.clone_from(&source.data);
+        self.weight = source.weight;
+    }
+}
+
+impl<K, V> Default for HashMapElement<K, V> {
+    fn default() -> Self {
+        HashMapElement {
+            key: None,
+            in_use: SLOT_EMPTY,
+            data: None,
+            weight: 0,
+        }
+    }
+}
+
+impl<K, V> HashMapElement<K, V> {
+    // Value of an occupied slot.
+    fn value(&self) -> &V {
+        self.data.as_ref().expect("occupied slot without a value")
+    }
+
+    fn value_mut(&mut self) -> &mut V {
+        self.data.as_mut().expect("occupied slot without a value")
+    }
+}
+
+// Called with (old table size, new table size) once a resize has allocated
+// its new table.
+type ResizeCallback = Box<dyn FnMut(usize, usize) + Send + Sync>;
+
+pub struct HashMapMap<K, V, S = DefaultHashBuilder> {
+    table_size: usize,
+    size: usize,
+    // Sum of the weights of the live entries.
+    total_weight: u64,
+    max_load_factor: f64,
+    growth_factor: f64,
+    auto_shrink: bool,
+    probing: ProbeStrategy,
+    // Longest probe sequence any stored key needed; lookups stop past it.
+    max_probe: usize,
+    data: Vec<HashMapElement<K, V>>,
+    hasher: S,
+    on_resize: Option<ResizeCallback>,
+    #[cfg(feature = "stats")]
+    stats: stats::Counters,
+}
+
+impl<K: Clone, V: Clone, S: Clone> Clone for HashMapMap<K, V, S> {
+    fn clone(&self) -> Self {
+        HashMapMap {
+            table_size: self.table_size,
+            size: self.size,
+            total_weight: self.total_weight,
+            max_load_factor: self.max_load_factor,
+            growth_factor: self.growth_factor,
+            auto_shrink: self.auto_shrink,
+            probing: self.probing,
+            max_probe: self.max_probe,
+            data: self.data.clone(),
+            hasher: self.hasher.clone(),
+            // Boxed closures cannot be cloned; a clone starts without one.
+            on_resize: None,
+            #[cfg(feature = "stats")]
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Copies `source` into `self`, reusing the slot vector rather than
+    /// allocating a new one when it is already big enough. `self` keeps its
+    /// own resize callback.
+    fn clone_from(&mut self, source: &Self) {
+        self.table_size = source.table_size;
+        self.size = source.size;
+        self.total_weight = source.total_weight;
+        self.max_load_factor = source.max_load_factor;
+        self.growth_factor = source.growth_factor;
+        self.auto_shrink = source.auto_shrink;
+        self.probing = source.probing;
+        self.max_probe = source.max_probe;
+        self.data.clone_from(&source.data);
+        self.hasher.clone_from(&source.hasher);
+        #[cfg(feature = "stats")]
+        self.stats.clone_from(&source.stats);
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HashMapMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(hashmap_iter(self)).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for HashMapMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.size != other.size {
+            return false;
+        }
+
+        hashmap_iter(self).all(|(key, value)| hashmap_get_ref(other, key) == Some(value))
+    }
+}
+
+impl<K, V, S> Eq for HashMapMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+// Slot order depends on capacity and insertion history, so each entry is
+// hashed on its own with a fixed hasher and the results are summed. Maps
+// that compare equal therefore hash equally whatever their layout.
+impl<K, V, S> Hash for HashMapMap<K, V, S>
+where
+    K: Hash,
+    V: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut sum: u64 = 0;
+        for (key, value) in hashmap_iter(self) {
+            let mut hasher = FnvHasher::default();
+            key.hash(&mut hasher);
+            value.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+        }
+        state.write_usize(self.size);
+        state.write_u64(sum);
+    }
+}
+
+impl<K, V, S> Index<&K> for HashMapMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        match hashmap_get_ref(self, key) {
+            Some(value) => value,
+            None => panic!("key {:?} is not in the hashmap", key),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> From<std::collections::HashMap<K, V, S>> for HashMapMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    fn from(src: std::collections::HashMap<K, V, S>) -> Self {
+        let mut m = hashmap_alloc(
+            hashmap_slots_for(src.len(), DEFAULT_MAX_LOAD_FACTOR),
+            src.hasher().clone(),
+        );
+        for (key, value) in src {
+            hashmap_put(&mut m, key, value).expect("hashmap_put failed while converting");
+        }
+        m
+    }
+}
+
+/// Moves every entry into a `std::collections::HashMap` that uses the same
+/// hasher.
+#[cfg(feature = "std")]
+pub fn hashmap_into_std<K, V, S>(m: MapT<K, V, S>) -> std::collections::HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let mut out = std::collections::HashMap::with_capacity_and_hasher(m.size, m.hasher);
+    for slot in m.data {
+        if slot.in_use != SLOT_IN_USE {
+            continue;
+        }
+        if let (Some(key), Some(value)) = (slot.key, slot.data) {
+            out.insert(key, value);
+        }
+    }
+    out
+}
+
+fn hashmap_alloc<K, V, S>(table_size: usize, hasher: S) -> MapT<K, V, S> {
+    let mut data = Vec::new();
+    data.resize_with(table_size, HashMapElement::default);
+    HashMapMap {
+        table_size,
+        size: 0,
+        total_weight: 0,
+        max_load_factor: DEFAULT_MAX_LOAD_FACTOR,
+        growth_factor: DEFAULT_GROWTH_FACTOR,
+        auto_shrink: false,
+        probing: ProbeStrategy::Linear,
+        max_probe: 0,
+        data,
+        hasher,
+        on_resize: None,
+        #[cfg(feature = "stats")]
+        stats: stats::Counters::default(),
+    }
+}
+
+/// Creates an empty map. The table is not allocated until the first insert.
+pub fn hashmap_new<K, V>() -> MapT<K, V> {
+    hashmap_alloc(0, DefaultHashBuilder::default())
+}
+
+/// Creates a map that can hold `n` entries under the default load factor
+/// without rehashing. The table size is rounded up to a power of two. With
+/// `n == 0` no table is allocated until the first insert, as with
+/// `hashmap_new`.
+pub fn hashmap_with_capacity<K, V>(n: usize) -> MapT<K, V> {
+    if n == 0 {
+        return hashmap_new();
+    }
+    hashmap_alloc(
+        hashmap_slots_for(n, DEFAULT_MAX_LOAD_FACTOR),
+        DefaultHashBuilder::default(),
+    )
+}
+
+/// Creates a map that resolves collisions with `probing`. `hashmap_new`
+/// always uses `ProbeStrategy::Linear`.
+pub fn hashmap_with_probing<K, V>(probing: ProbeStrategy) -> MapT<K, V> {
+    let mut m = hashmap_alloc(0, DefaultHashBuilder::default());
+    m.probing = probing;
+    m
+}
+
+/// Creates a map whose table grows by `factor` instead of doubling when it
+/// fills up. Under quadratic probing the new size is rounded up to a power
+/// of two, so factors below 2 only save memory with linear probing.
+pub fn hashmap_with_growth_factor<K, V>(factor: f64) -> Result<MapT<K, V>, MapError> {
+    let mut m = hashmap_alloc(0, DefaultHashBuilder::default());
+    hashmap_set_growth_factor(&mut m, factor)?;
+    Ok(m)
+}
+
+/// Creates a map that hashes its keys with `hasher` instead of the randomly
+/// seeded SipHash default, e.g. a faster hasher when DoS resistance is not a
+/// concern.
+pub fn hashmap_with_hasher<K, V, S>(hasher: S) -> MapT<K, V, S>
+where
+    S: BuildHasher,
+{
+    hashmap_alloc(0, hasher)
+}
+
+/// Creates a map whose hasher is seeded with `seed`, so its iteration order
+/// is reproducible. This opts out of the randomized default: anyone who
+/// knows the seed can pick keys that collide, so keep it to tests and
+/// trusted input.
+pub fn hashmap_with_seed<K, V>(seed: u64) -> MapT<K, V, SeededFnvBuildHasher> {
+    hashmap_alloc(0, SeededFnvBuildHasher::new(seed))
+}
+
+// Smallest power-of-two table that keeps `n` entries within `load_factor`.
+fn hashmap_slots_for(n: usize, load_factor: f64) -> usize {
+    hashmap_try_slots_for(n, load_factor).expect("hashmap capacity overflow")
+}
+
+// `hashmap_slots_for`, or None if the table would not fit in a usize.
+fn hashmap_try_slots_for(n: usize, load_factor: f64) -> Option<usize> {
+    let needed = ((n as f64 / load_factor) as usize).checked_add(1)?;
+    needed.max(MIN_CAP).checked_next_power_of_two()
+}
+
+pub fn hashmap_set_max_load_factor<K, V, S>(m: &mut MapT<K, V, S>, f: f64) -> Result<(), MapError> {
+    if !(f > 0.0 && f < 1.0) {
+        return Err(MapError::InvalidLoadFactor);
+    }
+    m.max_load_factor = f;
+
+    Ok(())
+}
+
+/// Sets how much the table grows on resize, see `hashmap_with_growth_factor`.
+pub fn hashmap_set_growth_factor<K, V, S>(m: &mut MapT<K, V, S>, f: f64) -> Result<(), MapError> {
+    if !(f > 1.0 && f.is_finite()) {
+        return Err(MapError::InvalidGrowthFactor);
+    }
+    m.growth_factor = f;
+
+    Ok(())
+}
+
+/// When enabled, removals that leave the map less than 15% full shrink the
+/// table with `hashmap_shrink_to_fit`. Off by default.
+pub fn hashmap_set_auto_shrink<K, V, S>(m: &mut MapT<K, V, S>, enabled: bool) {
+    m.auto_shrink = enabled;
+}
+
+/// Registers `f` to be called with the old and new table sizes on every
+/// resize, whether the table is growing, shrinking or being compacted. It
+/// runs once the new table has been allocated, before any entry moves into
+/// it; a resize whose allocation fails does not call it. Replaces any
+/// earlier callback. Clones of the map do not inherit it.
+pub fn hashmap_set_resize_callback<K, V, S, F>(m: &mut MapT<K, V, S>, f: F)
+where
+    F: FnMut(usize, usize) + Send + Sync + 'static,
+{
+    m.on_resize = Some(Box::new(f));
+}
+
+// Bucket `key` hashes to. Lookups may pass a borrowed form of the key, which
+// `Borrow` guarantees hashes the same as the owned one.
+fn hashmap_hash_key<K, V, S: BuildHasher, Q: Hash + ?Sized>(m: &MapT<K, V, S>, key: &Q) -> usize {
+    (m.hasher.hash_one(key) % m.table_size as u64) as usize
+}
+
+// Finds the slot `key` belongs in: the slot already holding it, or else the
+// first reusable one. Also returns the probe step at which that slot sits.
+fn hashmap_hash<K: Hash + Eq, V, S: BuildHasher>(
+    m: &MapT<K, V, S>,
+    key: &K,
+) -> Option<(usize, usize)> {
+    // A zero-slot table has no bucket to hash into; callers grow it first.
+    if m.table_size == 0 || m.size == m.table_size {
+        return None;
+    }
+    let base = hashmap_hash_key(m, key);
+    let mut tombstone = None;
+    for i in 0..m.table_size {
+        // No stored key sits further out than max_probe, so past it a free
+        // slot seen earlier is safe to take.
+        if i > m.max_probe && tombstone.is_some() {
+            break;
+        }
+        let curr = hashmap_probe_slot(m, base, i);
+        match m.data[curr].in_use {
+            SLOT_EMPTY => return Some(tombstone.unwrap_or((curr, i))),
+            SLOT_DELETED => {
+                tombstone = tombstone.or(Some((curr, i)));
+            }
+            _ => {
+                if m.data[curr].key.as_ref() == Some(key) {
+                    return Some((curr, i));
+                }
+            }
+        }
+    }
+
+    tombstone
+}
+
+// The `i`th slot visited when probing from `base`.
+fn hashmap_probe_slot<K, V, S>(m: &MapT<K, V, S>, base: usize, i: usize) -> usize {
+    match m.probing {
+        ProbeStrategy::Linear => (base + i) % m.table_size,
+        ProbeStrategy::Quadratic => (base + i * (i + 1) / 2) % m.table_size,
+    }
+}
+
+// Whether one more entry would push the map past its max load factor.
+fn hashmap_over_load<K, V, S>(m: &MapT<K, V, S>) -> bool {
+    (m.size + 1) as f64 > m.max_load_factor * m.table_size as f64
+}
+
+// Resolves the slot `key` should be written to, growing the table first if
+// the key is new and storing it would go over the load factor.
+fn hashmap_insert_slot<K, V, S>(m: &mut MapT<K, V, S>, key: &K) -> Result<(usize, usize), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    loop {
+        match hashmap_hash(m, key) {
+            Some((index, probe))
+                if m.data[index].in_use == SLOT_IN_USE || !hashmap_over_load(m) =>
+            {
+                return Ok((index, probe))
+            }
+            _ => hashmap_rehash(m)?,
+        }
+    }
+}
+
+fn hashmap_rehash<K, V, S>(m: &mut MapT<K, V, S>) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    if m.table_size == 0 {
+        return hashmap_resize(m, INIT_CAP);
+    }
+    // A table this large could never be allocated, so a size that does not
+    // even fit in a usize is reported the same way.
+    let scaled = m.table_size as f64 * m.growth_factor;
+    if scaled >= usize::MAX as f64 {
+        return Err(MapError::OutOfMemory);
+    }
+    let grown = m
+        .table_size
+        .checked_add(1)
+        .ok_or(MapError::OutOfMemory)?
+        .max(scaled as usize);
+    let new_size = match m.probing {
+        ProbeStrategy::Linear => grown,
+        // The triangular step only reaches every slot of a power-of-two table.
+        ProbeStrategy::Quadratic => grown
+            .checked_next_power_of_two()
+            .ok_or(MapError::OutOfMemory)?,
+    };
+    hashmap_resize(m, new_size)
+}
+
+// Moves every live entry into a fresh table of `new_size` slots. Only the
+// slot array and the counters derived from it are replaced; the hasher, load
+// and growth factors, probing strategy, auto-shrink flag and resize callback
+// all stay as configured.
+fn hashmap_resize<K, V, S>(m: &mut MapT<K, V, S>, new_size: usize) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let old_size = m.table_size;
+    // Allocate fallibly so running out of memory is reported as an error
+    // instead of aborting the process.
+    let mut curr = Vec::new();
+    curr.try_reserve_exact(new_size)
+        .map_err(|_| MapError::OutOfMemory)?;
+    curr.resize_with(new_size, HashMapElement::default);
+    if let Some(on_resize) = m.on_resize.as_mut() {
+        on_resize(old_size, new_size);
+    }
+    // let curr point to old data in memory
+    //let data field of m now point to new default-init'd vector.
+    mem::swap(&mut m.data, &mut curr);
+    m.table_size = new_size;
+    m.size = 0;
+    m.total_weight = 0;
+    m.max_probe = 0;
+    #[cfg(feature = "stats")]
+    m.stats.record_rehash();
+
+    for i in curr.into_iter().take(old_size) {
+        if i.in_use != SLOT_IN_USE {
+            continue;
+        }
+        if let (Some(key), Some(value)) = (i.key, i.data) {
+            hashmap_store(m, key, value, i.weight)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores `value` under `key` and returns the value it replaced, if the key
+/// was already present.
+pub fn hashmap_put<K, V, S>(m: &mut MapT<K, V, S>, key: K, value: V) -> Result<Option<V>, MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    hashmap_put_weighted(m, key, value, 0)
+}
+
+/// Like `hashmap_put`, but also records `weight` for the entry, e.g. its size
+/// in bytes. `hashmap_total_weight` sums the weights of the live entries.
+/// Overwriting a key replaces its weight; plain `hashmap_put` stores 0.
+/// Returns `MapError::WeightOverflow`, storing nothing, if the total weight
+/// would no longer fit in a u64.
+pub fn hashmap_put_weighted<K, V, S>(
+    m: &mut MapT<K, V, S>,
+    key: K,
+    value: V,
+    weight: u64,
+) -> Result<Option<V>, MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    #[cfg(feature = "stats")]
+    m.stats.record_insert();
+    hashmap_store(m, key, value, weight)
+}
+
+// `hashmap_put_weighted` without the bookkeeping, so rehashing does not
+// count as inserts.
+fn hashmap_store<K, V, S>(
+    m: &mut MapT<K, V, S>,
+    key: K,
+    value: V,
+    weight: u64,
+) -> Result<Option<V>, MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let (index, probe) = hashmap_insert_slot(m, &key)?;
+    let occupied = m.data[index].in_use == SLOT_IN_USE;
+    let replaced = if occupied { m.data[index].weight } else { 0 };
+    let total = (m.total_weight - replaced)
+        .checked_add(weight)
+        .ok_or(MapError::WeightOverflow)?;
+    if occupied {
+        let slot = &mut m.data[index];
+        m.total_weight = total;
+        slot.weight = weight;
+        return Ok(slot.data.replace(value));
+    }
+    hashmap_fill_slot(m, index, probe, key, value, weight);
+
+    Ok(None)
+}
+
+// Stores a new entry in the free slot `index`, found `probe` steps along its
+// probe sequence.
+fn hashmap_fill_slot<K, V, S>(
+    m: &mut MapT<K, V, S>,
+    index: usize,
+    probe: usize,
+    key: K,
+    value: V,
+    weight: u64,
+) {
+    let slot = &mut m.data[index];
+    slot.data = Some(value);
+    slot.key = Some(key);
+    slot.in_use = SLOT_IN_USE;
+    slot.weight = weight;
+    m.size += 1;
+    m.total_weight += weight;
+    m.max_probe = m.max_probe.max(probe);
+}
+
+/// A view into a single slot of the map, resolved once by `hashmap_entry`.
+pub struct Entry<'a, K, V, S = DefaultHashBuilder> {
+    map: &'a mut MapT<K, V, S>,
+    key: K,
+    index: usize,
+    probe: usize,
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S> {
+    fn is_occupied(&self) -> bool {
+        self.map.data[self.index].in_use == SLOT_IN_USE
+    }
+
+    /// Inserts `default` if the slot is empty, then returns the stored value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Like `or_insert`, but only calls `f` when the key is missing.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        if !self.is_occupied() {
+            hashmap_fill_slot(self.map, self.index, self.probe, self.key, f(), 0);
+            #[cfg(feature = "stats")]
+            self.map.stats.record_insert();
+        }
+        self.map.data[self.index].value_mut()
+    }
+
+    /// Inserts `V::default()` if the slot is empty, then returns the stored
+    /// value.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Runs `f` on the stored value if the key is already present.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if self.is_occupied() {
+            f(self.map.data[self.index].value_mut());
+        }
+        self
+    }
+}
+
+pub fn hashmap_entry<K, V, S>(m: &mut MapT<K, V, S>, key: K) -> Entry<'_, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let (index, probe) =
+        hashmap_insert_slot(m, &key).expect("hashmap_entry failed to grow the table");
+    Entry {
+        map: m,
+        key,
+        index,
+        probe,
+    }
+}
+
+/// Low-level handle on the slot a key resolves to, see `hashmap_raw_entry`.
+pub struct RawEntry<'a, K, V, S = DefaultHashBuilder> {
+    map: &'a mut MapT<K, V, S>,
+    key: K,
+    index: usize,
+    probe: usize,
+}
+
+impl<'a, K, V, S> RawEntry<'a, K, V, S> {
+    /// Table slot the key resolved to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_occupied(&self) -> bool {
+        self.map.data[self.index].in_use == SLOT_IN_USE
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// The value currently stored for the key, if it is present.
+    pub fn get(&self) -> Option<&V> {
+        if self.is_occupied() {
+            Some(self.map.data[self.index].value())
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        if self.is_occupied() {
+            Some(self.map.data[self.index].value_mut())
+        } else {
+            None
+        }
+    }
+
+    /// Stores `value` in the resolved slot without probing again, with a
+    /// weight of 0 like `hashmap_put`. Returns the value it replaced, if the
+    /// key was already present.
+    pub fn insert(self, value: V) -> Option<V> {
+        if self.is_occupied() {
+            let slot = &mut self.map.data[self.index];
+            self.map.total_weight -= slot.weight;
+            slot.weight = 0;
+            return slot.data.replace(value);
+        }
+        hashmap_fill_slot(self.map, self.index, self.probe, self.key, value, 0);
+        #[cfg(feature = "stats")]
+        self.map.stats.record_insert();
+        None
+    }
+}
+
+/// Resolves the slot for `key` once, growing the table first if a new key
+/// would not fit, and returns a handle for inspecting and filling it.
+pub fn hashmap_raw_entry<K, V, S>(m: &mut MapT<K, V, S>, key: K) -> RawEntry<'_, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let (index, probe) =
+        hashmap_insert_slot(m, &key).expect("hashmap_raw_entry failed to grow the table");
+    RawEntry {
+        map: m,
+        key,
+        index,
+        probe,
+    }
+}
+
+/// Returned by `hashmap_try_insert` when the key is already present. It hands
+/// back the key and the value that was not inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccupiedError<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Display for OccupiedError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "key {:?} is already in the hashmap, not inserting {:?}",
+            self.key, self.value
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for OccupiedError<K, V> {}
+
+/// Inserts `value` only if `key` is absent and returns a reference to it. An
+/// existing entry is left untouched and the pair comes back in the error.
+pub fn hashmap_try_insert<K, V, S>(
+    m: &mut MapT<K, V, S>,
+    key: K,
+    value: V,
+) -> Result<&mut V, OccupiedError<K, V>>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let entry = hashmap_entry(m, key);
+    if entry.is_occupied() {
+        return Err(OccupiedError {
+            key: entry.key,
+            value,
+        });
+    }
+
+    Ok(entry.or_insert(value))
+}
+
+/// Returns the value under `key`, computing and storing it with `f` first if
+/// the key is missing. The key is probed once.
+pub fn hashmap_get_or_insert_with<K, V, S, F>(m: &mut MapT<K, V, S>, key: K, f: F) -> &mut V
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnOnce() -> V,
+{
+    hashmap_entry(m, key).or_insert_with(f)
+}
+
+/// Returns the value under `key`, storing `value` first if the key is
+/// missing, and whether it did. On a hit `value` is dropped. The key is
+/// probed once.
+pub fn hashmap_get_or_insert<K, V, S>(m: &mut MapT<K, V, S>, key: K, value: V) -> (&mut V, bool)
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let entry = hashmap_entry(m, key);
+    let inserted = !entry.is_occupied();
+    (entry.or_insert(value), inserted)
+}
+
+/// Adds `delta` to the counter under `key`, starting it at `delta` if the
+/// key is missing, and returns the new count. Overflow behaves like `+`.
+/// The key is probed once.
+pub fn hashmap_increment<K, V, S>(m: &mut MapT<K, V, S>, key: K, delta: V) -> V
+where
+    K: Hash + Eq,
+    V: Copy + Add<Output = V>,
+    S: BuildHasher,
+{
+    let (count, inserted) = hashmap_get_or_insert(m, key, delta);
+    if !inserted {
+        *count = *count + delta;
+    }
+    *count
+}
+
+/// Integer types `hashmap_saturating_increment` can count with.
+pub trait SaturatingAdd: Copy {
+    fn saturating_add(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_saturating_add {
+    ($($t:ty)*) => {$(
+        impl SaturatingAdd for $t {
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$t>::saturating_add(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_saturating_add!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+/// Like `hashmap_increment`, but the count sticks at the type's bounds
+/// instead of overflowing.
+pub fn hashmap_saturating_increment<K, V, S>(m: &mut MapT<K, V, S>, key: K, delta: V) -> V
+where
+    K: Hash + Eq,
+    V: SaturatingAdd,
+    S: BuildHasher,
+{
+    let (count, inserted) = hashmap_get_or_insert(m, key, delta);
+    if !inserted {
+        *count = count.saturating_add(delta);
+    }
+    *count
+}
+
+fn hashmap_find<K, V, S, Q>(m: &MapT<K, V, S>, key: &Q) -> Option<usize>
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    hashmap_find_hashed(m, m.hasher.hash_one(key), key)
+}
+
+// `hashmap_find` for a key whose full hash is already known.
+fn hashmap_find_hashed<K, V, S, Q>(m: &MapT<K, V, S>, hash: u64, key: &Q) -> Option<usize>
+where
+    K: Borrow<Q>,
+    Q: Eq + ?Sized,
+{
+    #[cfg(feature = "stats")]
+    {
+        let mut probes = 0;
+        let found = hashmap_find_visiting(m, hash, key, |_| probes += 1);
+        m.stats.record_lookup(probes);
+        found
+    }
+    #[cfg(not(feature = "stats"))]
+    hashmap_find_visiting(m, hash, key, |_| {})
+}
+
+// The lookup walk behind `hashmap_find`, calling `visit` on every slot it
+// inspects.
+fn hashmap_find_visiting<K, V, S, Q, F>(
+    m: &MapT<K, V, S>,
+    hash: u64,
+    key: &Q,
+    mut visit: F,
+) -> Option<usize>
+where
+    K: Borrow<Q>,
+    Q: Eq + ?Sized,
+    F: FnMut(usize),
+{
+    if m.table_size == 0 {
+        return None;
+    }
+    let base = (hash % m.table_size as u64) as usize;
+    for i in 0..m.table_size.min(m.max_probe + 1) {
+        let curr = hashmap_probe_slot(m, base, i);
+        visit(curr);
+        match m.data[curr].in_use {
+            // Removal never leaves a hole inside a probe chain: linear
+            // probing shifts entries back and quadratic leaves tombstones.
+            SLOT_EMPTY => return None,
+            SLOT_IN_USE if m.data[curr].key.as_ref().map(Borrow::borrow) == Some(key) => {
+                return Some(curr)
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn hashmap_get<K, V, S, Q>(m: &mut MapT<K, V, S>, key: &Q) -> Option<V>
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    V: Copy,
+    S: BuildHasher,
+{
+    hashmap_find(m, key).map(|index| *m.data[index].value())
+}
+
+/// Returns a reference to the value for `key`. Like the other lookups, it
+/// accepts any borrowed form of the key, e.g. `&str` for `String` keys.
+pub fn hashmap_get_ref<'a, K, V, S, Q>(m: &'a MapT<K, V, S>, key: &Q) -> Option<&'a V>
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    hashmap_find(m, key).map(|index| m.data[index].value())
+}
+
+pub fn hashmap_get_mut<'a, K, V, S, Q>(m: &'a mut MapT<K, V, S>, key: &Q) -> Option<&'a mut V>
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    hashmap_find(m, key).map(move |index| m.data[index].value_mut())
+}
+
+/// A key bundled with its full hash, from `hashmap_prehash`. The `_with_hash`
+/// lookups use the stored hash instead of hashing the key again. It only
+/// matches in maps whose hasher produces the same hashes: the map it came
+/// from or a clone of it, or a map built with an identical hasher, such as
+/// the same seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreHashedKey<K> {
+    hash: u64,
+    key: K,
+}
+
+impl<K> PreHashedKey<K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+/// Hashes `key` once with the hasher of `m`, for repeated lookups.
+pub fn hashmap_prehash<K: Hash, V, S: BuildHasher>(m: &MapT<K, V, S>, key: K) -> PreHashedKey<K> {
+    PreHashedKey {
+        hash: m.hasher.hash_one(&key),
+        key,
+    }
+}
+
+pub fn hashmap_get_with_hash<'a, K: Eq, V, S>(
+    m: &'a MapT<K, V, S>,
+    key: &PreHashedKey<K>,
+) -> Option<&'a V> {
+    hashmap_find_hashed(m, key.hash, &key.key).map(|index| m.data[index].value())
+}
+
+pub fn hashmap_get_mut_with_hash<'a, K: Eq, V, S>(
+    m: &'a mut MapT<K, V, S>,
+    key: &PreHashedKey<K>,
+) -> Option<&'a mut V> {
+    hashmap_find_hashed(m, key.hash, &key.key).map(move |index| m.data[index].value_mut())
+}
+
+pub fn hashmap_contains_key_with_hash<K: Eq, V, S>(
+    m: &MapT<K, V, S>,
+    key: &PreHashedKey<K>,
+) -> bool {
+    hashmap_find_hashed(m, key.hash, &key.key).is_some()
+}
+
+/// Looks up every key in `keys` and returns the results in the same order.
+pub fn hashmap_get_all<'a, K: Hash + Eq, V, S: BuildHasher>(
+    m: &'a MapT<K, V, S>,
+    keys: &[K],
+) -> Vec<Option<&'a V>> {
+    keys.iter().map(|key| hashmap_get_ref(m, key)).collect()
+}
+
+/// Returns mutable references to the values of all `keys` at once. Returns
+/// None if any key is missing or the same key appears twice.
+pub fn hashmap_get_many_mut<'a, K, V, S, const N: usize>(
+    m: &'a mut MapT<K, V, S>,
+    keys: [&K; N],
+) -> Option<[&'a mut V; N]>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let mut indices = [0; N];
+    for (index, key) in indices.iter_mut().zip(keys) {
+        *index = hashmap_find(m, key)?;
+    }
+    let slots = m.data.get_disjoint_mut(indices).ok()?;
+    Some(slots.map(|slot| slot.value_mut()))
+}
+
+/// Swaps the values stored under `a` and `b` in place, weights included.
+/// Returns false, changing nothing, unless both keys are present.
+pub fn hashmap_swap_values<K, V, S, Q>(m: &mut MapT<K, V, S>, a: &Q, b: &Q) -> bool
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    let (ia, ib) = match (hashmap_find(m, a), hashmap_find(m, b)) {
+        (Some(ia), Some(ib)) => (ia, ib),
+        _ => return false,
+    };
+    if let Ok([sa, sb]) = m.data.get_disjoint_mut([ia, ib]) {
+        mem::swap(&mut sa.data, &mut sb.data);
+        mem::swap(&mut sa.weight, &mut sb.weight);
+    }
+    true
+}
+
+/// Applies `f` to the value stored under `key`. Returns false, without
+/// calling `f`, when the key is missing.
+pub fn hashmap_modify<K, V, S, F>(m: &mut MapT<K, V, S>, key: &K, f: F) -> bool
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnOnce(&mut V),
+{
+    match hashmap_get_mut(m, key) {
+        Some(value) => {
+            f(value);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn hashmap_contains_key<K, V, S, Q>(m: &MapT<K, V, S>, key: &Q) -> bool
+where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    hashmap_find(m, key).is_some()
+}
+
+// Slot of the first occupied entry in table order, if any.
+fn hashmap_first_occupied<K, V, S>(m: &MapT<K, V, S>) -> Option<usize> {
+    if hashmap_length(m) == 0 {
+        return None;
+    }
+
+    m.data.iter().position(|slot| slot.in_use == SLOT_IN_USE)
+}
+
+/// Returns some value from the map without removing it. Which one is
+/// unspecified; it is the first occupied slot in table order.
+pub fn hashmap_peek_any<K, V, S>(m: &MapT<K, V, S>) -> Option<&V> {
+    hashmap_first_occupied(m).map(|index| m.data[index].value())
+}
+
+/// Removes and returns some value from the map, picked like `hashmap_peek_any`.
+pub fn hashmap_pop_any<K, V, S>(m: &mut MapT<K, V, S>) -> Option<V>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let index = hashmap_first_occupied(m)?;
+    let (_, value, _) = hashmap_remove_at(m, index);
+    hashmap_maybe_shrink(m);
+    Some(value)
+}
+
+/// Removes and returns the entry with the smallest key. Keys are not
+/// ordered in the table, so finding it takes a full scan: O(n).
+pub fn hashmap_pop_min<K, V, S>(m: &mut MapT<K, V, S>) -> Option<(K, V)>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+{
+    let index = m
+        .data
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.in_use == SLOT_IN_USE)
+        .min_by(|(_, a), (_, b)| a.key.cmp(&b.key))
+        .map(|(index, _)| index)?;
+    let (key, value, _) = hashmap_remove_at(m, index);
+    hashmap_maybe_shrink(m);
+    Some((key, value))
+}
+
+#[deprecated(note = "use hashmap_peek_any or hashmap_pop_any instead")]
+pub fn hashmap_get_one<K, V, S>(m: &mut MapT<K, V, S>, remove: usize) -> Option<V>
+where
+    K: Hash + Eq,
+    V: Copy,
+    S: BuildHasher,
+{
+    if remove != 0 {
+        hashmap_pop_any(m)
+    } else {
+        hashmap_peek_any(m).copied()
+    }
+}
+
+pub fn hashmap_remove<K, V, S, Q>(m: &mut MapT<K, V, S>, key: &Q) -> Result<V, MapError>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    let curr = hashmap_find(m, key).ok_or(MapError::Missing)?;
+    let (_, value, _) = hashmap_remove_at(m, curr);
+    hashmap_maybe_shrink(m);
+
+    Ok(value)
+}
+
+/// Like `hashmap_remove`, but also hands back the stored key.
+pub fn hashmap_remove_entry<K, V, S, Q>(m: &mut MapT<K, V, S>, key: &Q) -> Option<(K, V)>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    let curr = hashmap_find(m, key)?;
+    let (key, value, _) = hashmap_remove_at(m, curr);
+    hashmap_maybe_shrink(m);
+    Some((key, value))
+}
+
+/// Moves the value stored under `old` to `new`. Returns false, leaving the
+/// map untouched, if `old` is missing or `new` is already present.
+pub fn hashmap_rekey<K, V, S>(m: &mut MapT<K, V, S>, old: &K, new: K) -> bool
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    if hashmap_contains_key(m, &new) {
+        return false;
+    }
+    let curr = match hashmap_find(m, old) {
+        Some(curr) => curr,
+        None => return false,
+    };
+    let (_, value, weight) = hashmap_remove_at(m, curr);
+    // The slot just freed guarantees room, so this never grows the table.
+    hashmap_put_weighted(m, new, value, weight).expect("hashmap_put failed while rekeying");
+    true
+}
+
+/// Removes each of `keys` that is present and returns how many were removed.
+/// Missing keys are skipped.
+pub fn hashmap_remove_many<'k, K, V, S, I>(m: &mut MapT<K, V, S>, keys: I) -> usize
+where
+    K: Hash + Eq + 'k,
+    S: BuildHasher,
+    I: IntoIterator<Item = &'k K>,
+{
+    keys.into_iter()
+        .filter(|key| hashmap_remove(m, key).is_ok())
+        .count()
+}
+
+/// Keeps only the entries for which `f` returns true, in one pass over the
+/// table.
+pub fn hashmap_retain<K, V, S, F>(m: &mut MapT<K, V, S>, mut f: F)
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    hashmap_remove_where(m, |key, value| !f(key, value), |_, _, _| {});
+    hashmap_maybe_shrink(m);
+}
+
+/// Moves every entry for which `f` returns true into a new map, which is
+/// returned, and leaves the rest in `m`. Entries keep their weights.
+pub fn hashmap_partition<K, V, S, F>(m: &mut MapT<K, V, S>, mut f: F) -> MapT<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+    F: FnMut(&K, &V) -> bool,
+{
+    let mut out = hashmap_alloc_like(m, 0);
+    hashmap_remove_where(
+        m,
+        |key, value| f(key, value),
+        |key, value, weight| {
+            hashmap_put_weighted(&mut out, key, value, weight)
+                .expect("hashmap_put failed while partitioning");
+        },
+    );
+    hashmap_maybe_shrink(m);
+    out
+}
+
+/// Removes every entry for which `f` returns true and yields them by value.
+/// The matching entries are all taken out before the first one is yielded,
+/// so dropping the iterator early still leaves them removed.
+pub fn hashmap_drain_filter<K, V, S, F>(m: &mut MapT<K, V, S>, mut f: F) -> vec::IntoIter<(K, V)>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    let mut removed = Vec::new();
+    hashmap_remove_where(
+        m,
+        |key, value| f(key, value),
+        |key, value, _| removed.push((key, value)),
+    );
+    hashmap_maybe_shrink(m);
+    removed.into_iter()
+}
+
+// Single pass over the table that removes every entry matching `pred` and
+// hands it to `sink` along with its weight.
+fn hashmap_remove_where<K, V, S, P, D>(m: &mut MapT<K, V, S>, mut pred: P, mut sink: D)
+where
+    K: Hash,
+    S: BuildHasher,
+    P: FnMut(&K, &mut V) -> bool,
+    D: FnMut(K, V, u64),
+{
+    if m.size == 0 {
+        return;
+    }
+    // Start just after an empty slot so closing a gap only ever pulls back
+    // entries the walk has not reached yet. The load factor keeps at least
+    // one slot free.
+    let start = m
+        .data
+        .iter()
+        .position(|slot| slot.in_use != SLOT_IN_USE)
+        .unwrap_or(0);
+    let mut curr = (start + 1) % m.table_size;
+    while curr != start {
+        if m.data[curr].in_use == SLOT_IN_USE {
+            let slot = &mut m.data[curr];
+            if let (Some(key), Some(value)) = (&slot.key, &mut slot.data) {
+                if pred(key, value) {
+                    let (key, value, weight) = hashmap_remove_at(m, curr);
+                    sink(key, value, weight);
+                    /* An unvisited entry may have moved into this slot */
+                    continue;
+                }
+            }
+        }
+        curr = (curr + 1) % m.table_size;
+    }
+}
+
+/// Removes every entry but keeps the table allocated at its current size.
+pub fn hashmap_clear<K, V, S>(m: &mut MapT<K, V, S>) {
+    for slot in m.data.iter_mut() {
+        *slot = HashMapElement::default();
+    }
+    m.size = 0;
+    m.total_weight = 0;
+    m.max_probe = 0;
+}
+
+/// Clears the map and refills it with the `n` pairs `f(0)..f(n)`, reusing the
+/// current table. The table only grows if the new entries do not fit.
+/// Repeated keys overwrite each other as with `hashmap_put`.
+pub fn hashmap_reset_with<K, V, S, F>(
+    m: &mut MapT<K, V, S>,
+    n: usize,
+    mut f: F,
+) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnMut(usize) -> (K, V),
+{
+    hashmap_clear(m);
+    for i in 0..n {
+        let (key, value) = f(i);
+        hashmap_put(m, key, value)?;
+    }
+
+    Ok(())
+}
+
+// Empties an occupied slot, repairs the probe chain behind it and returns
+// what it held: key, value and weight.
+fn hashmap_remove_at<K, V, S>(m: &mut MapT<K, V, S>, index: usize) -> (K, V, u64)
+where
+    K: Hash,
+    S: BuildHasher,
+{
+    /* Move the value out and blank out the fields */
+    let value = m.data[index]
+        .data
+        .take()
+        .expect("occupied slot without a value");
+    let key = m.data[index]
+        .key
+        .take()
+        .expect("occupied slot without a key");
+    /* Reduce the size */
+    m.size -= 1;
+    let weight = mem::take(&mut m.data[index].weight);
+    m.total_weight -= weight;
+    match m.probing {
+        ProbeStrategy::Linear => {
+            m.data[index].in_use = SLOT_EMPTY;
+            hashmap_close_gap(m, index);
+        }
+        ProbeStrategy::Quadratic => m.data[index].in_use = SLOT_DELETED,
+    }
+
+    (key, value, weight)
+}
+
+// Backward-shift deletion: walk forward from a freshly emptied slot and pull
+// back every entry whose ideal bucket is at or before the hole, so probe
+// chains that ran through the removed slot stay unbroken. Linear probing only.
+fn hashmap_close_gap<K: Hash, V, S: BuildHasher>(m: &mut MapT<K, V, S>, mut hole: usize) {
+    let mut curr = (hole + 1) % m.table_size;
+    while m.data[curr].in_use == SLOT_IN_USE {
+        let ideal = match &m.data[curr].key {
+            Some(key) => hashmap_hash_key(m, key),
+            None => break,
+        };
+        let dist_ideal = (curr + m.table_size - ideal) % m.table_size;
+        let dist_hole = (curr + m.table_size - hole) % m.table_size;
+        if dist_ideal >= dist_hole {
+            m.data.swap(hole, curr);
+            hole = curr;
+        }
+        curr = (curr + 1) % m.table_size;
+    }
+}
+
+/// Iterator over the occupied slots of a map, see `hashmap_iter`.
+pub struct Iter<'a, K, V> {
+    slots: slice::Iter<'a, HashMapElement<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if slot.in_use == SLOT_IN_USE {
+                if let (Some(key), Some(value)) = (&slot.key, &slot.data) {
+                    return Some((key, value));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Mutable iterator over the occupied slots of a map, see `hashmap_iter_mut`.
+pub struct IterMut<'a, K, V> {
+    slots: slice::IterMut<'a, HashMapElement<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if slot.in_use == SLOT_IN_USE {
+                if let (Some(key), Some(value)) = (&slot.key, &mut slot.data) {
+                    return Some((key, value));
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn hashmap_iter<K, V, S>(m: &MapT<K, V, S>) -> Iter<'_, K, V> {
+    Iter {
+        slots: m.data.iter(),
+    }
+}
+
+pub fn hashmap_iter_mut<K, V, S>(m: &mut MapT<K, V, S>) -> IterMut<'_, K, V> {
+    IterMut {
+        slots: m.data.iter_mut(),
+    }
+}
+
+/// Iterator returned by `hashmap_iter_from`.
+pub struct IterFrom<'a, K, V> {
+    head: Iter<'a, K, V>,
+    tail: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for IterFrom<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.next().or_else(|| self.tail.next())
+    }
+}
+
+/// Iterates every entry once, starting at the bucket `key` hashes to and
+/// wrapping around the end of the table. The order follows the table layout,
+/// not the keys, and changes whenever the table is rehashed.
+pub fn hashmap_iter_from<'a, K, V, S, Q>(m: &'a MapT<K, V, S>, key: &Q) -> IterFrom<'a, K, V>
+where
+    K: Borrow<Q>,
+    Q: Hash + ?Sized,
+    S: BuildHasher,
+{
+    let start = if m.table_size == 0 {
+        0
+    } else {
+        hashmap_hash_key(m, key)
+    };
+    let (before, after) = m.data.split_at(start);
+    IterFrom {
+        head: Iter {
+            slots: after.iter(),
+        },
+        tail: Iter {
+            slots: before.iter(),
+        },
+    }
+}
+
+/// Iterates the entries in ascending key order. The table has no order of
+/// its own, so this collects and sorts every entry up front: O(n log n) time
+/// and O(n) extra memory.
+pub fn hashmap_iter_sorted<K: Ord, V, S>(m: &MapT<K, V, S>) -> vec::IntoIter<(&K, &V)> {
+    let mut entries: Vec<_> = hashmap_iter(m).collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries.into_iter()
+}
+
+/// Iterator over the keys of a map, see `hashmap_keys`.
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+/// Iterator over the values of a map, see `hashmap_values`.
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+/// Mutable iterator over the values of a map, see `hashmap_values_mut`.
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+pub fn hashmap_keys<K, V, S>(m: &MapT<K, V, S>) -> Keys<'_, K, V> {
+    Keys {
+        inner: hashmap_iter(m),
+    }
+}
+
+pub fn hashmap_values<K, V, S>(m: &MapT<K, V, S>) -> Values<'_, K, V> {
+    Values {
+        inner: hashmap_iter(m),
+    }
+}
+
+/// Updates values in place. Only occupied slots are visited, and the map
+/// cannot be inserted into or removed from while the iterator is alive.
+pub fn hashmap_values_mut<K, V, S>(m: &mut MapT<K, V, S>) -> ValuesMut<'_, K, V> {
+    ValuesMut {
+        inner: hashmap_iter_mut(m),
+    }
+}
+
+/// Draining iterator over a map's entries, see `hashmap_drain`.
+pub struct Drain<'a, K, V> {
+    // The map's slot array, taken out so the map reads as empty until the
+    // drained table is put back on drop.
+    table: Vec<HashMapElement<K, V>>,
+    next: usize,
+    data: &'a mut Vec<HashMapElement<K, V>>,
+    table_size: &'a mut usize,
+}
+
+impl<'a, K, V> Drain<'a, K, V> {
+    // Like `next`, but also yields the entry's weight.
+    fn next_weighted(&mut self) -> Option<(K, V, u64)> {
+        while let Some(slot) = self.table.get_mut(self.next) {
+            self.next += 1;
+            if slot.in_use == SLOT_IN_USE {
+                slot.in_use = SLOT_EMPTY;
+                let weight = mem::take(&mut slot.weight);
+                if let (Some(key), Some(value)) = (slot.key.take(), slot.data.take()) {
+                    return Some((key, value, weight));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a, K, V> Iterator for Drain<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_weighted().map(|(key, value, _)| (key, value))
+    }
+}
+
+impl<'a, K, V> Drop for Drain<'a, K, V> {
+    fn drop(&mut self) {
+        for _ in &mut *self {}
+        *self.table_size = self.table.len();
+        mem::swap(self.data, &mut self.table);
+    }
+}
+
+/// Moves every entry out of the map. The map is empty once the iterator is
+/// dropped, whether or not it was fully consumed, and keeps its capacity.
+/// If the iterator is leaked instead, the map is left empty with no table.
+pub fn hashmap_drain<K, V, S>(m: &mut MapT<K, V, S>) -> Drain<'_, K, V> {
+    m.size = 0;
+    m.total_weight = 0;
+    m.max_probe = 0;
+    m.table_size = 0;
+    Drain {
+        table: mem::take(&mut m.data),
+        next: 0,
+        data: &mut m.data,
+        table_size: &mut m.table_size,
+    }
+}
+
+/// Owning iterator over a map's entries, see `IntoIterator for HashMapMap`.
+pub struct IntoIter<K, V> {
+    slots: vec::IntoIter<HashMapElement<K, V>>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in &mut self.slots {
+            if slot.in_use == SLOT_IN_USE {
+                if let (Some(key), Some(value)) = (slot.key, slot.data) {
+                    return Some((key, value));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<K, V, S> IntoIterator for HashMapMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter {
+            slots: self.data.into_iter(),
+        }
+    }
+}
+
+// Empty map sized for `n` entries that shares the hasher, load factor and
+// probing strategy of `m`.
+fn hashmap_alloc_like<K, V, J, U, S>(m: &MapT<K, V, S>, n: usize) -> MapT<J, U, S>
+where
+    S: Clone,
+{
+    let mut out = hashmap_alloc(hashmap_slots_for(n, m.max_load_factor), m.hasher.clone());
+    out.max_load_factor = m.max_load_factor;
+    out.growth_factor = m.growth_factor;
+    out.auto_shrink = m.auto_shrink;
+    out.probing = m.probing;
+    out
+}
+
+/// Builds a new map with the same keys and each value replaced by `f(value)`.
+/// The new map shares the hasher and configuration of `m`, which is left
+/// untouched.
+pub fn hashmap_map_values<K, V, U, S, F>(m: &MapT<K, V, S>, mut f: F) -> MapT<K, U, S>
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher + Clone,
+    F: FnMut(&V) -> U,
+{
+    let mut out = hashmap_alloc_like(m, m.size);
+    for (key, value) in hashmap_iter(m) {
+        hashmap_put(&mut out, key.clone(), f(value)).expect("hashmap_put failed while mapping");
+    }
+    out
+}
+
+/// Replaces every value in place with `f(key, &value)`. Unlike
+/// `hashmap_map_values` the value type stays the same and no new table is
+/// allocated.
+pub fn hashmap_replace_values<K, V, S, F>(m: &mut MapT<K, V, S>, mut f: F)
+where
+    F: FnMut(&K, &V) -> V,
+{
+    for (key, value) in hashmap_iter_mut(m) {
+        *value = f(key, value);
+    }
+}
+
+/// Consumes the map and returns one keyed by its values. When several keys
+/// share a value, the one visited last in slot order wins.
+pub fn hashmap_invert<K, V, S>(mut m: MapT<K, V, S>) -> MapT<V, K, S>
+where
+    V: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    let mut out = hashmap_alloc_like(&m, m.size);
+    for (key, value) in hashmap_drain(&mut m) {
+        hashmap_put(&mut out, value, key).expect("hashmap_put failed while inverting");
+    }
+    out
+}
+
+/// Consumes the map and returns a read-only view of it. `thaw` on the view
+/// gives the map back.
+pub fn hashmap_freeze<K, V, S>(m: MapT<K, V, S>) -> FrozenHashMap<K, V, S> {
+    FrozenHashMap::from(m)
+}
+
+/// Grows the table once, if needed, so that `additional` more entries fit
+/// under the max load factor without rehashing. Panics if the required
+/// capacity overflows a usize; see `hashmap_try_reserve`.
+pub fn hashmap_reserve<K, V, S>(m: &mut MapT<K, V, S>, additional: usize) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let wanted = m
+        .size
+        .checked_add(additional)
+        .expect("hashmap capacity overflow");
+    let needed = hashmap_slots_for(wanted, m.max_load_factor);
+    if needed > m.table_size {
+        hashmap_resize(m, needed)?;
+    }
+
+    Ok(())
+}
+
+/// Like `hashmap_reserve`, but a request too large to size or allocate
+/// returns `MapError::OutOfMemory` instead of panicking or aborting. The map
+/// is unchanged on error.
+pub fn hashmap_try_reserve<K, V, S>(
+    m: &mut MapT<K, V, S>,
+    additional: usize,
+) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let needed = m
+        .size
+        .checked_add(additional)
+        .and_then(|n| hashmap_try_slots_for(n, m.max_load_factor))
+        .ok_or(MapError::OutOfMemory)?;
+    if needed > m.table_size {
+        hashmap_resize(m, needed)?;
+    }
+
+    Ok(())
+}
+
+/// Rehashes into the smallest power-of-two table, never below `MIN_CAP`,
+/// that keeps the current entries under the max load factor.
+pub fn hashmap_shrink_to_fit<K, V, S>(m: &mut MapT<K, V, S>) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    let needed = hashmap_slots_for(m.size, m.max_load_factor);
+    if needed < m.table_size {
+        hashmap_resize(m, needed)?;
+    }
+
+    Ok(())
+}
+
+// Called after removals: shrinks the table if auto-shrink is on and the map
+// has become sparse. Shrinking is only an optimisation and a failed resize
+// leaves the map intact, so an allocation failure is ignored here rather
+// than failing the removal that triggered it.
+fn hashmap_maybe_shrink<K, V, S>(m: &mut MapT<K, V, S>)
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    if m.auto_shrink && (m.size as f64) < AUTO_SHRINK_LOAD_FACTOR * m.table_size as f64 {
+        let _ = hashmap_shrink_to_fit(m);
+    }
+}
+
+/// Rehashes every entry into a fresh table of the same size. This clears
+/// the tombstones quadratic probing leaves behind on removal, shortening the
+/// probe chains that ran through them. Linear probing never leaves
+/// tombstones, but compacting still repacks the entries. A map that has not
+/// allocated its table yet is left alone.
+pub fn hashmap_compact<K, V, S>(m: &mut MapT<K, V, S>) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    if m.table_size == 0 {
+        return Ok(());
+    }
+    hashmap_resize(m, m.table_size)
+}
+
+/// Returns the key of the first entry, in slot order, whose value equals
+/// `value`. Values are not indexed, so this scans the whole table: O(n).
+pub fn hashmap_find_key<'a, K, V: PartialEq, S>(m: &'a MapT<K, V, S>, value: &V) -> Option<&'a K> {
+    hashmap_iter(m)
+        .find(|(_, v)| *v == value)
+        .map(|(key, _)| key)
+}
+
+/// Whether any entry holds `value`. Values are not indexed, so this scans
+/// the table until the first match: O(n).
+pub fn hashmap_contains_value<K, V: PartialEq, S>(m: &MapT<K, V, S>, value: &V) -> bool {
+    hashmap_values(m).any(|v| v == value)
+}
+
+/// Smallest key in the map, found with one scan of the table.
+pub fn hashmap_min_key<K: Ord, V, S>(m: &MapT<K, V, S>) -> Option<&K> {
+    hashmap_keys(m).min()
+}
+
+/// Largest key in the map, found with one scan of the table.
+pub fn hashmap_max_key<K: Ord, V, S>(m: &MapT<K, V, S>) -> Option<&K> {
+    hashmap_keys(m).max()
+}
+
+/// Number of entries for which `f` returns true.
+pub fn hashmap_count_matching<K, V, S, F>(m: &MapT<K, V, S>, mut f: F) -> usize
+where
+    F: FnMut(&K, &V) -> bool,
+{
+    hashmap_fold(m, 0, |count, key, value| count + usize::from(f(key, value)))
+}
+
+/// Threads `acc` through every entry in slot order and returns the result.
+pub fn hashmap_fold<K, V, S, A, F>(m: &MapT<K, V, S>, init: A, mut f: F) -> A
+where
+    F: FnMut(A, &K, &V) -> A,
+{
+    let mut acc = init;
+    for slot in &m.data {
+        if slot.in_use == SLOT_IN_USE {
+            if let (Some(key), Some(value)) = (&slot.key, &slot.data) {
+                acc = f(acc, key, value);
+            }
+        }
+    }
+    acc
+}
+
+/// Inserts every pair from `iter`, overwriting keys that are already present.
+/// The table is grown once up front from the iterator's size hint.
+pub fn hashmap_extend<K, V, S, I>(m: &mut MapT<K, V, S>, iter: I) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let iter = iter.into_iter();
+    let (lower, _) = iter.size_hint();
+    hashmap_reserve(m, lower)?;
+    for (key, value) in iter {
+        hashmap_put(m, key, value)?;
+    }
+
+    Ok(())
+}
+
+/// Moves every entry of `src` into `dest`, weights included, overwriting keys
+/// `dest` already has. `src` is left empty but keeps its capacity. On error,
+/// e.g. `MapError::WeightOverflow`, both maps are left untouched.
+pub fn hashmap_append<K, V, S, T>(
+    dest: &mut MapT<K, V, S>,
+    src: &mut MapT<K, V, T>,
+) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    // Everything that can fail is checked before the first entry moves.
+    let mut new_keys = 0;
+    let mut replaced = 0;
+    for (key, _) in hashmap_iter(src) {
+        match hashmap_find(dest, key) {
+            Some(index) => replaced += dest.data[index].weight,
+            None => new_keys += 1,
+        }
+    }
+    (dest.total_weight - replaced)
+        .checked_add(src.total_weight)
+        .ok_or(MapError::WeightOverflow)?;
+    hashmap_reserve(dest, new_keys)?;
+    // Drop the weight of every entry about to be overwritten up front, so the
+    // running total never passes the final one checked above.
+    dest.total_weight -= replaced;
+    for (key, _) in hashmap_iter(src) {
+        if let Some(index) = hashmap_find(dest, key) {
+            dest.data[index].weight = 0;
+        }
+    }
+    let mut drain = hashmap_drain(src);
+    while let Some((key, value, weight)) = drain.next_weighted() {
+        hashmap_put_weighted(dest, key, value, weight)?;
+    }
+
+    Ok(())
+}
+
+/// Where two maps differ, see `hashmap_diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapDiff<'a, K, V> {
+    /// Keys present only in the first map.
+    pub only_in_a: Vec<&'a K>,
+    /// Keys present only in the second map.
+    pub only_in_b: Vec<&'a K>,
+    /// Keys present in both with different values, as (key, a's, b's).
+    pub differing: Vec<(&'a K, &'a V, &'a V)>,
+}
+
+impl<'a, K, V> MapDiff<'a, K, V> {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty()
+    }
+}
+
+/// Lists the keys that only one map has and the shared keys whose values
+/// differ. Each map is scanned once. Meant for making failed equality
+/// checks readable.
+pub fn hashmap_diff<'a, K, V, S, T>(a: &'a MapT<K, V, S>, b: &'a MapT<K, V, T>) -> MapDiff<'a, K, V>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+    T: BuildHasher,
+{
+    let mut diff = MapDiff {
+        only_in_a: Vec::new(),
+        only_in_b: Vec::new(),
+        differing: Vec::new(),
+    };
+    for (key, va) in hashmap_iter(a) {
+        match hashmap_get_ref(b, key) {
+            None => diff.only_in_a.push(key),
+            Some(vb) if va != vb => diff.differing.push((key, va, vb)),
+            Some(_) => {}
+        }
+    }
+    diff.only_in_b = hashmap_keys(b)
+        .filter(|key| !hashmap_contains_key(a, *key))
+        .collect();
+    diff
+}
+
+/// Counts how many entries fall into each bucket returned by `f`.
+pub fn hashmap_count_by<K, V, S, B, F>(m: &MapT<K, V, S>, mut f: F) -> MapT<B, usize>
+where
+    B: Hash + Eq,
+    F: FnMut(&K, &V) -> B,
+{
+    let mut counts = hashmap_new();
+    for (key, value) in hashmap_iter(m) {
+        *hashmap_entry(&mut counts, f(key, value)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Moves every entry of `b` into `a`. For keys present in both maps the
+/// stored value becomes `f(key, a_value, b_value)` and keeps the weight it
+/// had in `a`; entries only in `b` bring their weight along. On error, e.g.
+/// `MapError::WeightOverflow`, `a` is left untouched.
+pub fn hashmap_merge<K, V, S, T, F>(
+    a: &mut MapT<K, V, S>,
+    mut b: MapT<K, V, T>,
+    mut f: F,
+) -> Result<(), MapError>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    F: FnMut(&K, V, V) -> V,
+{
+    // Everything that can fail is checked before the first entry moves.
+    let mut new_keys = 0;
+    let mut total = a.total_weight;
+    for slot in b.data.iter().filter(|slot| slot.in_use == SLOT_IN_USE) {
+        if let Some(key) = &slot.key {
+            if hashmap_find(a, key).is_none() {
+                new_keys += 1;
+                total = total
+                    .checked_add(slot.weight)
+                    .ok_or(MapError::WeightOverflow)?;
+            }
+        }
+    }
+    hashmap_reserve(a, new_keys)?;
+    let mut drain = hashmap_drain(&mut b);
+    while let Some((key, value, weight)) = drain.next_weighted() {
+        match hashmap_find(a, &key) {
+            Some(index) => {
+                let slot = &mut a.data[index];
+                let old = slot.data.take().expect("occupied slot without a value");
+                slot.data = Some(f(&key, old, value));
+            }
+            None => {
+                hashmap_put_weighted(a, key, value, weight)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMapMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        let mut m = hashmap_alloc(
+            hashmap_slots_for(lower, DEFAULT_MAX_LOAD_FACTOR),
+            S::default(),
+        );
+        for (key, value) in iter {
+            hashmap_put(&mut m, key, value).expect("hashmap_put failed while collecting");
+        }
+        m
+    }
+}
+
+/// Copies every entry out into a vector, in slot order.
+pub fn hashmap_to_vec<K: Clone, V: Clone, S>(m: &MapT<K, V, S>) -> Vec<(K, V)> {
+    hashmap_iter(m)
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Builds a map from `pairs`. Later pairs overwrite earlier ones with the
+/// same key.
+pub fn hashmap_from_vec<K, V>(pairs: Vec<(K, V)>) -> MapT<K, V>
+where
+    K: Hash + Eq,
+{
+    pairs.into_iter().collect()
+}
+
+/// Counters collected since the map was created. Only available with the
+/// `stats` feature; without it nothing is recorded.
+#[cfg(feature = "stats")]
+pub fn hashmap_stats<K, V, S>(m: &MapT<K, V, S>) -> MapStats {
+    m.stats.snapshot()
+}
+
+/// Sum of the weights of the live entries, see `hashmap_put_weighted`.
+pub fn hashmap_total_weight<K, V, S>(m: &MapT<K, V, S>) -> u64 {
+    m.total_weight
+}
+
+pub fn hashmap_length<K, V, S>(m: &MapT<K, V, S>) -> usize {
+    m.size
+}
+
+/// Longest probe sequence any stored key needs: how many steps past its
+/// ideal bucket the furthest entry sits. A large value points to clustering
+/// or a weak hasher.
+pub fn hashmap_max_probe_len<K: Hash, V, S: BuildHasher>(m: &MapT<K, V, S>) -> usize {
+    (0..m.table_size)
+        .filter(|&index| m.data[index].in_use == SLOT_IN_USE)
+        .map(|index| hashmap_probe_distance(m, index))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks the table's internal invariants: the recorded size matches the
+/// occupied slots, and every stored key is found by a lookup at its own slot,
+/// which also rules out duplicate keys. Returns a description of the first
+/// violation. It scans the whole table, so keep it to tests and debugging.
+pub fn hashmap_validate<K, V, S>(m: &MapT<K, V, S>) -> Result<(), String>
+where
+    K: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+{
+    if m.data.len() != m.table_size {
+        return Err(format!(
+            "table_size is {} but the table has {} slots",
+            m.table_size,
+            m.data.len()
+        ));
+    }
+    let mut occupied = 0;
+    for (index, slot) in m.data.iter().enumerate() {
+        if slot.in_use != SLOT_IN_USE {
+            continue;
+        }
+        occupied += 1;
+        let key = match &slot.key {
+            Some(key) => key,
+            None => return Err(format!("slot {} is in use but holds no key", index)),
+        };
+        match hashmap_find(m, key) {
+            Some(found) if found == index => {}
+            Some(found) => {
+                return Err(format!(
+                    "key {:?} is stored in both slot {} and slot {}",
+                    key, found, index
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "key {:?} in slot {} is not reachable from its hash",
+                    key, index
+                ))
+            }
+        }
+    }
+    let weight: u64 = m
+        .data
+        .iter()
+        .filter(|slot| slot.in_use == SLOT_IN_USE)
+        .map(|slot| slot.weight)
+        .sum();
+    if weight != m.total_weight {
+        return Err(format!(
+            "total_weight is {} but the live entries weigh {}",
+            m.total_weight, weight
+        ));
+    }
+    if occupied != m.size {
+        return Err(format!(
+            "size is {} but {} slots are in use",
+            m.size, occupied
+        ));
+    }
+
+    Ok(())
+}
+
+/// Keys stored in more than one slot, each listed once. A healthy map always
+/// returns an empty vector; see `hashmap_validate` for a wider check.
+pub fn hashmap_find_duplicates<K: Hash + Eq + Clone, V, S>(m: &MapT<K, V, S>) -> Vec<K> {
+    let mut seen = hashmap_with_capacity::<&K, usize>(m.size);
+    for key in hashmap_keys(m) {
+        *hashmap_entry(&mut seen, key).or_insert(0) += 1;
+    }
+    hashmap_iter(&seen)
+        .filter(|(_, &count)| count > 1)
+        .map(|(&key, _)| key.clone())
+        .collect()
+}
+
+/// Slots a lookup of `key` inspects, in order, ending at the key's slot or
+/// wherever the search gives up. Meant for debugging collisions.
+pub fn hashmap_probe_trace<K: Hash + Eq, V, S: BuildHasher>(
+    m: &MapT<K, V, S>,
+    key: &K,
+) -> Vec<usize> {
+    let mut trace = Vec::new();
+    hashmap_find_visiting(m, m.hasher.hash_one(key), key, |index| trace.push(index));
+    trace
+}
+
+/// Bucket `key` hashes to before any probing, under the map's hasher and
+/// current table size, so it changes when the table is resized. Keys that
+/// share a bucket collide. 0 while the map has no slots.
+pub fn hashmap_bucket_of<K, V, S, Q>(m: &MapT<K, V, S>, key: &Q) -> usize
+where
+    K: Borrow<Q>,
+    Q: Hash + ?Sized,
+    S: BuildHasher,
+{
+    if m.table_size == 0 {
+        return 0;
+    }
+    hashmap_hash_key(m, key)
+}
+
+// Number of probe steps from the ideal bucket of the entry at `index` to
+// `index` itself.
+fn hashmap_probe_distance<K: Hash, V, S: BuildHasher>(m: &MapT<K, V, S>, index: usize) -> usize {
+    let base = match &m.data[index].key {
+        Some(key) => hashmap_hash_key(m, key),
+        None => return 0,
+    };
+    match m.probing {
+        ProbeStrategy::Linear => (index + m.table_size - base) % m.table_size,
+        ProbeStrategy::Quadratic => (0..m.table_size)
+            .find(|&i| hashmap_probe_slot(m, base, i) == index)
+            .unwrap_or(0),
+    }
+}
+
+/// Number of slots holding a tombstone left by a removal under quadratic
+/// probing. `hashmap_compact` clears them.
+pub fn hashmap_tombstones<K, V, S>(m: &MapT<K, V, S>) -> usize {
+    m.data
+        .iter()
+        .filter(|slot| slot.in_use == SLOT_DELETED)
+        .count()
+}
+
+pub fn hashmap_capacity<K, V, S>(m: &MapT<K, V, S>) -> usize {
+    m.table_size
+}
+
+pub fn hashmap_is_empty<K, V, S>(m: &MapT<K, V, S>) -> bool {
+    m.size == 0
+}
+
+/// Fraction of slots holding a live entry.
+pub fn hashmap_load_factor<K, V, S>(m: &MapT<K, V, S>) -> f64 {
+    if m.table_size == 0 {
+        return 0.0;
+    }
+    m.size as f64 / m.table_size as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use core::hash::BuildHasherDefault;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    // Hashes an integer key to its own value, so a test can pick which keys
+    // collide: in a 1024-slot table, k and k + 1024 share a bucket.
+    #[derive(Default)]
+    struct IdentityHasher(u64);
+
+    impl Hasher for IdentityHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &b in bytes.iter().rev() {
+                self.0 = (self.0 << 8) | u64::from(b);
+            }
+        }
+    }
+
+    type IdentityState = BuildHasherDefault<IdentityHasher>;
+
+    fn identity_map<K>() -> MapT<K, u32, IdentityState> {
+        hashmap_with_hasher(IdentityState::default())
+    }
+
+    // Neither Clone nor Default, so it can only be moved in and out.
+    #[derive(Debug, PartialEq)]
+    struct NoClone(u32);
+
+    #[test]
+    fn stores_values_that_are_not_clone() {
+        let mut m = hashmap_new::<u32, NoClone>();
+        for k in 0..2000 {
+            hashmap_put(&mut m, k, NoClone(k)).unwrap();
+        }
+        assert_eq!(hashmap_remove(&mut m, &7), Ok(NoClone(7)));
+        assert_eq!(hashmap_get_ref(&m, &8), Some(&NoClone(8)));
+        assert_eq!(hashmap_length(&m), 1999);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn overwriting_adjusts_the_total_weight() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut m, 1, 10, 100).unwrap();
+        hashmap_put_weighted(&mut m, 2, 20, 50).unwrap();
+        hashmap_put_weighted(&mut m, 1, 11, 30).unwrap();
+        assert_eq!(hashmap_total_weight(&m), 80);
+        hashmap_remove(&mut m, &2).unwrap();
+        assert_eq!(hashmap_total_weight(&m), 30);
+    }
+
+    #[test]
+    fn weight_overflow_is_an_error() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut m, 1, 10, u64::MAX).unwrap();
+        assert_eq!(
+            hashmap_put_weighted(&mut m, 2, 20, 1),
+            Err(MapError::WeightOverflow)
+        );
+        assert!(!hashmap_contains_key(&m, &2));
+        assert_eq!(hashmap_total_weight(&m), u64::MAX);
+        // Replacing the heavy entry frees its weight first.
+        hashmap_put_weighted(&mut m, 1, 11, u64::MAX).unwrap();
+        assert_eq!(hashmap_get_ref(&m, &1), Some(&11));
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn weights_move_with_their_entries() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..10 {
+            hashmap_put_weighted(&mut m, k, k, 100).unwrap();
+        }
+        let odd = hashmap_partition(&mut m, |k, _| k % 2 == 1);
+        assert_eq!(hashmap_total_weight(&m), 500);
+        assert_eq!(hashmap_total_weight(&odd), 500);
+
+        let mut dest = hashmap_new::<u32, u32>();
+        let mut src = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut src, 1, 1, 50).unwrap();
+        hashmap_append(&mut dest, &mut src).unwrap();
+        assert_eq!(hashmap_total_weight(&dest), 50);
+        assert_eq!(hashmap_total_weight(&src), 0);
+
+        let mut b = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut b, 1, 2, 7).unwrap();
+        hashmap_put_weighted(&mut b, 2, 3, 9).unwrap();
+        hashmap_merge(&mut dest, b, |_, x, y| x + y).unwrap();
+        assert_eq!(hashmap_get_ref(&dest, &1), Some(&3));
+        assert_eq!(hashmap_total_weight(&dest), 59);
+        for map in [&m, &odd, &dest] {
+            hashmap_validate(map).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_burst_of_removals_shrinks_once() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_set_auto_shrink(&mut m, true);
+        for k in 0..1000 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&m), 2048);
+        let resizes = Arc::new(AtomicUsize::new(0));
+        let seen = resizes.clone();
+        hashmap_set_resize_callback(&mut m, move |_, _| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+        for k in 0..900 {
+            hashmap_remove(&mut m, &k).unwrap();
+        }
+        assert_eq!(resizes.load(Ordering::Relaxed), 1);
+        assert_eq!(hashmap_capacity(&m), 512);
+        for k in 900..1000 {
+            assert_eq!(hashmap_get_ref(&m, &k), Some(&k));
+        }
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "hashmap capacity overflow")]
+    fn reserve_panics_on_capacity_overflow() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_put(&mut m, 1, 1).unwrap();
+        let _ = hashmap_reserve(&mut m, usize::MAX);
+    }
+
+    #[test]
+    fn get_many_mut_rejects_missing_and_repeated_keys() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..10 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        if let Some([a, b]) = hashmap_get_many_mut(&mut m, [&1, &2]) {
+            mem::swap(a, b);
+        }
+        assert_eq!(hashmap_get_ref(&m, &1), Some(&2));
+        assert_eq!(hashmap_get_ref(&m, &2), Some(&1));
+        assert!(hashmap_get_many_mut(&mut m, [&3, &3]).is_none());
+        assert!(hashmap_get_many_mut(&mut m, [&3, &42]).is_none());
+    }
+
+    #[test]
+    fn compacting_an_unallocated_map_does_not_resize() {
+        let mut m = hashmap_new::<u32, u32>();
+        let resizes = Arc::new(AtomicUsize::new(0));
+        let seen = resizes.clone();
+        hashmap_set_resize_callback(&mut m, move |_, _| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+        hashmap_compact(&mut m).unwrap();
+        assert_eq!(resizes.load(Ordering::Relaxed), 0);
+        #[cfg(feature = "stats")]
+        assert_eq!(hashmap_stats(&m).rehashes, 0);
+        hashmap_put(&mut m, 1, 1).unwrap();
+        hashmap_compact(&mut m).unwrap();
+        assert_eq!(resizes.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn colliding_keys_wrap_around_the_end_of_the_table() {
+        let mut m = identity_map::<u32>();
+        // All four hash to the last slot of the 1024-slot table, so the
+        // probe run has to wrap to the front.
+        let keys = [1023, 2047, 3071, 4095];
+        for &k in &keys {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&m), 1024);
+        for (&k, slot) in keys.iter().zip([1023, 0, 1, 2]) {
+            assert_eq!(hashmap_get_ref(&m, &k), Some(&k));
+            assert_eq!(hashmap_probe_trace(&m, &k).last(), Some(&slot));
+        }
+        // Keys that land in the wrapped run still find their own slots.
+        hashmap_put(&mut m, 0, 0).unwrap();
+        assert_eq!(hashmap_probe_trace(&m, &0).last(), Some(&3));
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn keys_survive_two_rehashes() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..2000 {
+            hashmap_put(&mut m, k, k * 3).unwrap();
+        }
+        // 1024 -> 2048 -> 4096
+        assert_eq!(hashmap_capacity(&m), 4096);
+        assert_eq!(hashmap_length(&m), 2000);
+        for k in 0..2000 {
+            assert_eq!(hashmap_get_ref(&m, &k), Some(&(k * 3)));
+        }
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn extreme_keys_hash_without_overflow() {
+        let mut m = hashmap_new::<usize, u32>();
+        let mut raw = identity_map::<usize>();
+        for k in [0, 1, usize::MAX - 1, usize::MAX] {
+            hashmap_put(&mut m, k, 1).unwrap();
+            hashmap_put(&mut raw, k, 1).unwrap();
+        }
+        assert_eq!(hashmap_get_ref(&m, &usize::MAX), Some(&1));
+        // The full-width hash is reduced modulo the table size.
+        assert_eq!(hashmap_bucket_of(&raw, &usize::MAX), 1023);
+        assert_eq!(hashmap_get_ref(&raw, &usize::MAX), Some(&1));
+        hashmap_validate(&m).unwrap();
+        hashmap_validate(&raw).unwrap();
+    }
+
+    #[test]
+    fn retain_keeps_colliding_survivors_reachable() {
+        for probing in [ProbeStrategy::Linear, ProbeStrategy::Quadratic] {
+            let mut m = identity_map::<u32>();
+            m.probing = probing;
+            // Long chains: every multiple of 1024 starts in bucket 0, and
+            // the small keys sit in the slots those chains run through.
+            for k in 0..40 {
+                hashmap_put(&mut m, k * 1024, k).unwrap();
+                hashmap_put(&mut m, k, k).unwrap();
+            }
+            hashmap_retain(&mut m, |_, v| *v % 3 != 0);
+            for k in 0..40 {
+                assert_eq!(hashmap_contains_key(&m, &(k * 1024)), k % 3 != 0);
+                assert_eq!(hashmap_contains_key(&m, &k), k % 3 != 0);
+            }
+            hashmap_validate(&m).unwrap();
+        }
+    }
+
+    #[test]
+    fn key_zero_is_an_ordinary_key() {
+        for probing in [ProbeStrategy::Linear, ProbeStrategy::Quadratic] {
+            let mut m = identity_map::<u32>();
+            m.probing = probing;
+            // 0, 1024 and 2048 share bucket 0, with key 0 first in the chain.
+            for k in [0, 1024, 2048, 1] {
+                hashmap_put(&mut m, k, k + 7).unwrap();
+            }
+            hashmap_remove(&mut m, &1024).unwrap();
+            assert_eq!(hashmap_get_ref(&m, &0), Some(&7));
+            assert_eq!(hashmap_get_ref(&m, &2048), Some(&2055));
+            assert!(!hashmap_contains_key(&m, &1024));
+            hashmap_remove(&mut m, &0).unwrap();
+            assert!(!hashmap_contains_key(&m, &0));
+            assert_eq!(hashmap_get_ref(&m, &2048), Some(&2055));
+            hashmap_put(&mut m, 0, 9).unwrap();
+            assert_eq!(hashmap_get_ref(&m, &0), Some(&9));
+            assert_eq!(hashmap_length(&m), 3);
+            hashmap_validate(&m).unwrap();
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_after_bulk_removal() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..10_000 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        for k in 100..10_000 {
+            hashmap_remove(&mut m, &k).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&m), 16384);
+        hashmap_shrink_to_fit(&mut m).unwrap();
+        assert_eq!(hashmap_capacity(&m), 256);
+        for k in 0..100 {
+            assert_eq!(hashmap_get_ref(&m, &k), Some(&k));
+        }
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn overwriting_one_key_does_not_grow_the_map() {
+        let mut m = hashmap_new::<u32, u32>();
+        for i in 0..2000 {
+            hashmap_put(&mut m, 5, i).unwrap();
+        }
+        assert_eq!(hashmap_length(&m), 1);
+        assert_eq!(hashmap_capacity(&m), 1024);
+        assert_eq!(hashmap_get_ref(&m, &5), Some(&1999));
+    }
+
+    #[test]
+    fn lookups_stay_bounded_near_the_load_limit() {
+        let mut m = hashmap_with_seed::<u32, u32>(42);
+        // 716 is the most a 1024-slot table holds under the 0.7 load factor.
+        for k in 0..716 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&m), 1024);
+        assert_eq!(hashmap_max_probe_len(&m), m.max_probe);
+        // Every lookup, hit or miss, stops within the longest stored probe
+        // sequence instead of scanning the table.
+        let limit = m.max_probe + 1;
+        for k in 0..10_000 {
+            assert!(hashmap_probe_trace(&m, &k).len() <= limit);
+        }
+        assert!(limit < 1024 / 8, "probe limit {} too long", limit);
+    }
+
+    #[test]
+    fn merge_resolves_conflicts_and_grows_past_the_load_factor() {
+        let mut a = hashmap_new::<u32, u32>();
+        for k in 0..700 {
+            hashmap_put(&mut a, k, 1).unwrap();
+        }
+        let mut b = hashmap_new::<u32, u32>();
+        for k in 650..750 {
+            hashmap_put(&mut b, k, 10).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&a), 1024);
+        hashmap_merge(&mut a, b, |_, x, y| x + y).unwrap();
+        assert_eq!(hashmap_length(&a), 750);
+        assert_eq!(hashmap_capacity(&a), 2048);
+        for k in 0..750 {
+            let expected = match k {
+                0..=649 => 1,
+                650..=699 => 11,
+                _ => 10,
+            };
+            assert_eq!(hashmap_get_ref(&a, &k), Some(&expected));
+        }
+        hashmap_validate(&a).unwrap();
+    }
+
+    #[test]
+    fn rekey_success_missing_and_colliding() {
+        let mut m = hashmap_new::<u32, NoClone>();
+        hashmap_put(&mut m, 1, NoClone(10)).unwrap();
+        hashmap_put(&mut m, 2, NoClone(20)).unwrap();
+
+        assert!(hashmap_rekey(&mut m, &1, 3));
+        assert!(!hashmap_contains_key(&m, &1));
+        assert_eq!(hashmap_get_ref(&m, &3), Some(&NoClone(10)));
+        assert_eq!(hashmap_length(&m), 2);
+
+        assert!(!hashmap_rekey(&mut m, &1, 4));
+        assert!(!hashmap_contains_key(&m, &4));
+
+        assert!(!hashmap_rekey(&mut m, &2, 3));
+        assert_eq!(hashmap_get_ref(&m, &2), Some(&NoClone(20)));
+        assert_eq!(hashmap_get_ref(&m, &3), Some(&NoClone(10)));
+        assert_eq!(hashmap_length(&m), 2);
+        hashmap_validate(&m).unwrap();
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = FnvHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn equal_maps_hash_equally_whatever_their_layout() {
+        let mut a = hashmap_new::<u32, u32>();
+        for k in 0..500 {
+            hashmap_put(&mut a, k, k * 2).unwrap();
+        }
+        let mut b = hashmap_with_capacity::<u32, u32>(5000);
+        for k in (0..600).rev() {
+            hashmap_put(&mut b, k, k * 2).unwrap();
+        }
+        for k in 500..600 {
+            hashmap_remove(&mut b, &k).unwrap();
+        }
+        assert!(a == b);
+        assert_ne!(hashmap_capacity(&a), hashmap_capacity(&b));
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        hashmap_put(&mut b, 0, 1).unwrap();
+        assert!(a != b);
+        assert_ne!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn clone_from_reuses_the_slot_vector() {
+        let mut source = hashmap_new::<u32, u32>();
+        for k in 0..500 {
+            hashmap_put(&mut source, k, k).unwrap();
+        }
+        let mut dest = hashmap_new::<u32, u32>();
+        hashmap_put(&mut dest, 9999, 0).unwrap();
+        assert_eq!(hashmap_capacity(&dest), hashmap_capacity(&source));
+        let slots = dest.data.as_ptr();
+
+        dest.clone_from(&source);
+        assert_eq!(dest.data.as_ptr(), slots);
+        assert!(dest == source);
+        assert!(!hashmap_contains_key(&dest, &9999));
+        hashmap_validate(&dest).unwrap();
+    }
+
+    #[test]
+    fn new_maps_allocate_on_first_insert() {
+        let mut m = hashmap_new::<u32, u32>();
+        assert_eq!(hashmap_capacity(&m), 0);
+        assert_eq!(m.data.capacity(), 0);
+        let sized = hashmap_with_capacity::<u32, u32>(0);
+        assert_eq!(sized.data.capacity(), 0);
+
+        hashmap_put(&mut m, 1, 1).unwrap();
+        assert_eq!(hashmap_capacity(&m), INIT_CAP);
+        assert_eq!(hashmap_get_ref(&m, &1), Some(&1));
+    }
+
+    #[test]
+    fn reads_and_removes_on_a_zero_slot_table() {
+        let mut m = hashmap_new::<u32, u32>();
+        assert_eq!(hashmap_get_ref(&m, &1), None);
+        assert_eq!(hashmap_get(&mut m, &1), None);
+        assert_eq!(hashmap_get_mut(&mut m, &1), None);
+        assert!(!hashmap_contains_key(&m, &1));
+        assert_eq!(hashmap_remove(&mut m, &1), Err(MapError::Missing));
+        assert_eq!(hashmap_remove_entry(&mut m, &1), None);
+        assert_eq!(hashmap_pop_any(&mut m), None);
+        assert!(hashmap_probe_trace(&m, &1).is_empty());
+        assert_eq!(hashmap_capacity(&m), 0);
+    }
+
+    #[test]
+    fn configuration_survives_a_resize() {
+        let mut m = hashmap_with_seed::<u32, u32>(7);
+        hashmap_set_max_load_factor(&mut m, 0.5).unwrap();
+        hashmap_set_growth_factor(&mut m, 3.0).unwrap();
+        hashmap_set_auto_shrink(&mut m, true);
+        let resizes = Arc::new(AtomicUsize::new(0));
+        let seen = resizes.clone();
+        hashmap_set_resize_callback(&mut m, move |_, _| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+        for k in 0..513 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        // The 513th key went over half of 1024 slots and grew the table 3x.
+        assert_eq!(hashmap_capacity(&m), 3072);
+        assert_eq!(m.max_load_factor, 0.5);
+        assert_eq!(m.growth_factor, 3.0);
+        assert!(m.auto_shrink);
+        assert_eq!(
+            m.hasher.hash_one(5u32),
+            SeededFnvBuildHasher::new(7).hash_one(5u32)
+        );
+        // The factor still applies to the new table: 1536 entries fit, the
+        // next one grows it again.
+        for k in 513..1536 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        assert_eq!(hashmap_capacity(&m), 3072);
+        hashmap_put(&mut m, 1536, 1536).unwrap();
+        assert_eq!(hashmap_capacity(&m), 9216);
+        assert_eq!(resizes.load(Ordering::Relaxed), 3);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn saturating_increment_sticks_at_the_bounds() {
+        let mut m = hashmap_new::<&str, u8>();
+        assert_eq!(hashmap_increment(&mut m, "a", 3), 3);
+        assert_eq!(hashmap_increment(&mut m, "a", 4), 7);
+        assert_eq!(hashmap_saturating_increment(&mut m, "b", 250), 250);
+        assert_eq!(hashmap_saturating_increment(&mut m, "b", 10), u8::MAX);
+        assert_eq!(hashmap_saturating_increment(&mut m, "b", 1), u8::MAX);
+        assert_eq!(hashmap_length(&m), 2);
+
+        let mut signed = hashmap_new::<u32, i32>();
+        hashmap_saturating_increment(&mut signed, 1, i32::MIN + 1);
+        assert_eq!(hashmap_saturating_increment(&mut signed, 1, -5), i32::MIN);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn plain_increment_overflows_like_add() {
+        let mut m = hashmap_new::<u32, u8>();
+        hashmap_increment(&mut m, 0, 200);
+        hashmap_increment(&mut m, 0, 100);
+    }
+
+    #[test]
+    fn weight_overflow_leaves_append_and_merge_inputs_untouched() {
+        let mut dest = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut dest, 0, 0, u64::MAX).unwrap();
+        let mut src = hashmap_new::<u32, u32>();
+        for k in 1..50 {
+            hashmap_put_weighted(&mut src, k, k, 1).unwrap();
+        }
+        assert_eq!(
+            hashmap_append(&mut dest, &mut src),
+            Err(MapError::WeightOverflow)
+        );
+        assert_eq!(hashmap_length(&src), 49);
+        assert_eq!(hashmap_total_weight(&src), 49);
+        assert_eq!(hashmap_length(&dest), 1);
+        assert_eq!(hashmap_total_weight(&dest), u64::MAX);
+
+        let b = src.clone();
+        assert_eq!(
+            hashmap_merge(&mut dest, b, |_, x, y| x + y),
+            Err(MapError::WeightOverflow)
+        );
+        assert_eq!(hashmap_length(&dest), 1);
+        assert_eq!(hashmap_get_ref(&dest, &0), Some(&0));
+        assert_eq!(hashmap_total_weight(&dest), u64::MAX);
+
+        // Overwriting the heavy key frees its weight, so this one fits.
+        hashmap_put_weighted(&mut src, 0, 1, u64::MAX - 49).unwrap();
+        hashmap_append(&mut dest, &mut src).unwrap();
+        assert_eq!(hashmap_length(&dest), 50);
+        assert_eq!(hashmap_get_ref(&dest, &0), Some(&1));
+        assert_eq!(hashmap_total_weight(&dest), u64::MAX);
+        assert!(hashmap_is_empty(&src));
+        hashmap_validate(&dest).unwrap();
+    }
+
+    #[test]
+    fn huge_growth_factor_reports_out_of_memory() {
+        for probing in [ProbeStrategy::Linear, ProbeStrategy::Quadratic] {
+            let mut m = hashmap_new::<u32, u32>();
+            m.probing = probing;
+            hashmap_set_growth_factor(&mut m, 1e300).unwrap();
+            let mut k = 0;
+            let err = loop {
+                match hashmap_put(&mut m, k, k) {
+                    Ok(_) => k += 1,
+                    Err(e) => break e,
+                }
+            };
+            assert_eq!(err, MapError::OutOfMemory);
+            assert_eq!(hashmap_length(&m), k as usize);
+            assert_eq!(hashmap_capacity(&m), INIT_CAP);
+            for i in 0..k {
+                assert_eq!(hashmap_get_ref(&m, &i), Some(&i));
+            }
+            hashmap_validate(&m).unwrap();
+        }
+    }
+
+    #[test]
+    fn raw_entry_insert_resets_the_replaced_weight() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_put_weighted(&mut m, 1, 10, 7).unwrap();
+        hashmap_put_weighted(&mut m, 2, 20, 5).unwrap();
+        assert_eq!(hashmap_raw_entry(&mut m, 1).insert(11), Some(10));
+        assert_eq!(hashmap_get_ref(&m, &1), Some(&11));
+        assert_eq!(hashmap_total_weight(&m), 5);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn leaked_drain_leaves_a_consistent_map() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..100 {
+            hashmap_put_weighted(&mut m, k, k, 1).unwrap();
+        }
+        let mut drain = hashmap_drain(&mut m);
+        assert!(drain.next().is_some());
+        mem::forget(drain);
+        hashmap_validate(&m).unwrap();
+        assert!(hashmap_is_empty(&m));
+        assert_eq!(hashmap_total_weight(&m), 0);
+        assert_eq!(hashmap_get_ref(&m, &5), None);
+        hashmap_put(&mut m, 5, 50).unwrap();
+        assert_eq!(hashmap_get_ref(&m, &5), Some(&50));
+
+        let cap = hashmap_capacity(&m);
+        assert_eq!(hashmap_drain(&mut m).count(), 1);
+        assert_eq!(hashmap_capacity(&m), cap);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn get_mut_changes_are_seen_by_get_ref() {
+        let mut m = hashmap_new::<u32, u32>();
+        hashmap_put(&mut m, 1, 10).unwrap();
+        hashmap_put(&mut m, 2, 20).unwrap();
+        *hashmap_get_mut(&mut m, &1).unwrap() += 5;
+        assert_eq!(hashmap_get_ref(&m, &1), Some(&15));
+        assert_eq!(hashmap_get_ref(&m, &2), Some(&20));
+        assert_eq!(hashmap_get_mut(&mut m, &3), None);
+    }
+
+    #[test]
+    fn clear_keeps_capacity_and_behaves_like_new() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..2000 {
+            hashmap_put_weighted(&mut m, k, k, 1).unwrap();
+        }
+        let cap = hashmap_capacity(&m);
+        hashmap_clear(&mut m);
+        assert_eq!(hashmap_capacity(&m), cap);
+        assert!(hashmap_is_empty(&m));
+        assert_eq!(hashmap_total_weight(&m), 0);
+        assert_eq!(hashmap_iter(&m).count(), 0);
+        assert_eq!(hashmap_get_ref(&m, &7), None);
+        hashmap_validate(&m).unwrap();
+
+        for k in 0..10 {
+            assert_eq!(hashmap_put(&mut m, k, k * 2).unwrap(), None);
+        }
+        assert_eq!(hashmap_length(&m), 10);
+        assert_eq!(hashmap_get_ref(&m, &7), Some(&14));
+        assert_eq!(hashmap_get_ref(&m, &1500), None);
+        assert_eq!(hashmap_capacity(&m), cap);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn tables_grow_past_sixteen_bit_sizes() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..100_000 {
+            hashmap_put(&mut m, k, k ^ 0x5555).unwrap();
+        }
+        assert!(hashmap_capacity(&m) > 1 << 16);
+        assert_eq!(hashmap_length(&m), 100_000);
+        for k in 0..100_000 {
+            assert_eq!(hashmap_get_ref(&m, &k), Some(&(k ^ 0x5555)));
+        }
+        assert_eq!(hashmap_get_ref(&m, &100_000), None);
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn keys_differing_only_above_bit_31_stay_distinct() {
+        let low = 5u64;
+        let high = 5 | 1 << 40;
+
+        let mut m = hashmap_new::<u64, u32>();
+        hashmap_put(&mut m, low, 1).unwrap();
+        hashmap_put(&mut m, high, 2).unwrap();
+        assert_eq!(hashmap_length(&m), 2);
+        assert_eq!(hashmap_get_ref(&m, &low), Some(&1));
+        assert_eq!(hashmap_get_ref(&m, &high), Some(&2));
+
+        // With the identity hasher the two hashes differ only above bit 31.
+        let mut m = identity_map::<u64>();
+        hashmap_put(&mut m, low, 1).unwrap();
+        hashmap_put(&mut m, high, 2).unwrap();
+        assert_eq!(hashmap_length(&m), 2);
+        assert_eq!(hashmap_get_ref(&m, &low), Some(&1));
+        assert_eq!(hashmap_get_ref(&m, &high), Some(&2));
+        hashmap_remove(&mut m, &low).unwrap();
+        assert_eq!(hashmap_get_ref(&m, &high), Some(&2));
+    }
+
+    #[test]
+    fn changing_a_clone_leaves_the_original_alone() {
+        let mut original = hashmap_new::<u32, u32>();
+        for k in 0..100 {
+            hashmap_put_weighted(&mut original, k, k, 1).unwrap();
+        }
+        let mut copy = original.clone();
+        hashmap_put(&mut copy, 0, 1000).unwrap();
+        hashmap_remove(&mut copy, &1).unwrap();
+        hashmap_put(&mut copy, 500, 500).unwrap();
+        for k in 1000..3000 {
+            hashmap_put(&mut copy, k, k).unwrap();
+        }
+
+        assert_eq!(hashmap_length(&original), 100);
+        assert_eq!(hashmap_total_weight(&original), 100);
+        for k in 0..100 {
+            assert_eq!(hashmap_get_ref(&original, &k), Some(&k));
+        }
+        assert_eq!(hashmap_get_ref(&original, &500), None);
+        assert_eq!(hashmap_get_ref(&copy, &0), Some(&1000));
+        assert_eq!(hashmap_get_ref(&copy, &1), None);
+        hashmap_validate(&original).unwrap();
+        hashmap_validate(&copy).unwrap();
+    }
+
+    #[test]
+    fn partition_splits_colliding_keys() {
+        let mut m = identity_map::<u32>();
+        // Every k and k + 1024 share a home slot in the first table.
+        for k in 0..20 {
+            hashmap_put(&mut m, k, k).unwrap();
+            hashmap_put(&mut m, k + 1024, k + 1024).unwrap();
+        }
+        let before = m.clone();
+        let evens = hashmap_partition(&mut m, |key, _| key % 2 == 0);
+
+        assert_eq!(hashmap_length(&evens), 20);
+        assert_eq!(hashmap_length(&m), 20);
+        for (key, value) in hashmap_iter(&before) {
+            let (keep, other) = if key % 2 == 0 {
+                (&evens, &m)
+            } else {
+                (&m, &evens)
+            };
+            assert_eq!(hashmap_get_ref(keep, key), Some(value));
+            assert!(!hashmap_contains_key(other, key));
+        }
+        hashmap_validate(&evens).unwrap();
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn append_moves_the_union_and_overwrites_conflicts() {
+        let mut dest = hashmap_new::<u32, u32>();
+        for k in 0..60 {
+            hashmap_put(&mut dest, k, k).unwrap();
+        }
+        let mut src = hashmap_new::<u32, u32>();
+        for k in 40..100 {
+            hashmap_put(&mut src, k, k + 1000).unwrap();
+        }
+        let src_cap = hashmap_capacity(&src);
+        hashmap_append(&mut dest, &mut src).unwrap();
+
+        assert_eq!(hashmap_length(&src), 0);
+        assert_eq!(hashmap_capacity(&src), src_cap);
+        assert_eq!(hashmap_length(&dest), 100);
+        for k in 0..40 {
+            assert_eq!(hashmap_get_ref(&dest, &k), Some(&k));
+        }
+        for k in 40..100 {
+            assert_eq!(hashmap_get_ref(&dest, &k), Some(&(k + 1000)));
+        }
+        hashmap_validate(&dest).unwrap();
+        hashmap_validate(&src).unwrap();
+    }
+
+    #[test]
+    fn fold_sums_the_values() {
+        let mut m = hashmap_new::<u32, u32>();
+        let mut expected = 0u64;
+        for k in 0..500 {
+            hashmap_put(&mut m, k, k * 3).unwrap();
+            expected += u64::from(k * 3);
+        }
+        let sum = hashmap_fold(&m, 0u64, |acc, _, value| acc + u64::from(*value));
+        assert_eq!(sum, expected);
+        assert_eq!(
+            hashmap_fold(&hashmap_new::<u32, u32>(), 7, |acc, _, _| acc + 1),
+            7
+        );
+    }
+
+    #[test]
+    fn count_by_buckets_every_entry() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..253 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        let counts = hashmap_count_by(&m, |key, _| key % 10);
+        assert_eq!(hashmap_length(&counts), 10);
+        for bucket in 0..10 {
+            let expected = (0..253).filter(|k| k % 10 == bucket).count();
+            assert_eq!(hashmap_get_ref(&counts, &bucket), Some(&expected));
+        }
+        let total: usize = hashmap_iter(&counts).map(|(_, n)| *n).sum();
+        assert_eq!(total, hashmap_length(&m));
+    }
+
+    #[test]
+    fn pop_min_yields_keys_in_ascending_order() {
+        let mut m = hashmap_new::<u32, u32>();
+        for i in 0..200u32 {
+            let key = i.wrapping_mul(2_654_435_761) % 10_000;
+            hashmap_put(&mut m, key, i).unwrap();
+        }
+        let len = hashmap_length(&m);
+        let mut popped = Vec::new();
+        while let Some((key, _)) = hashmap_pop_min(&mut m) {
+            popped.push(key);
+        }
+        assert_eq!(popped.len(), len);
+        assert!(popped.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(hashmap_is_empty(&m));
+    }
+
+    #[test]
+    fn get_all_answers_in_request_order() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in [2, 4, 6] {
+            hashmap_put(&mut m, k, k * 10).unwrap();
+        }
+        let got = hashmap_get_all(&m, &[6, 1, 2, 3, 4, 6]);
+        assert_eq!(
+            got,
+            vec![Some(&60), None, Some(&20), None, Some(&40), Some(&60)]
+        );
+        assert!(hashmap_get_all(&m, &[]).is_empty());
+    }
+
+    #[test]
+    fn or_default_groups_items_by_key() {
+        let pairs = [(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (2, 'e'), (1, 'f')];
+        let mut groups = hashmap_new::<u32, Vec<char>>();
+        for (key, item) in pairs {
+            hashmap_entry(&mut groups, key).or_default().push(item);
+        }
+        assert_eq!(hashmap_length(&groups), 3);
+        assert_eq!(hashmap_get_ref(&groups, &1), Some(&vec!['a', 'c', 'f']));
+        assert_eq!(hashmap_get_ref(&groups, &2), Some(&vec!['b', 'e']));
+        assert_eq!(hashmap_get_ref(&groups, &3), Some(&vec!['d']));
+    }
+
+    #[test]
+    fn compact_clears_quadratic_tombstones() {
+        let mut m = hashmap_new::<u32, u32>();
+        m.probing = ProbeStrategy::Quadratic;
+        for k in 0..600 {
+            hashmap_put(&mut m, k, k).unwrap();
+        }
+        for k in (0..600).step_by(2) {
+            hashmap_remove(&mut m, &k).unwrap();
+        }
+        assert!(hashmap_tombstones(&m) > 0);
+        hashmap_compact(&mut m).unwrap();
+        assert_eq!(hashmap_tombstones(&m), 0);
+        assert_eq!(hashmap_length(&m), 300);
+        for k in 0..600 {
+            let expected = if k % 2 == 1 { Some(&k) } else { None };
+            assert_eq!(hashmap_get_ref(&m, &k), expected);
+        }
+        hashmap_validate(&m).unwrap();
+    }
+
+    #[test]
+    fn count_matching_counts_even_keys() {
+        let mut m = hashmap_new::<u32, u32>();
+        for k in 0..301 {
+            hashmap_put(&mut m, k, 0).unwrap();
+        }
+        assert_eq!(hashmap_count_matching(&m, |key, _| key % 2 == 0), 151);
+        assert_eq!(hashmap_count_matching(&m, |_, _| false), 0);
+    }
+}
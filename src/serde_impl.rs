@@ -0,0 +1,98 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{
+    hashmap_alloc, hashmap_iter, hashmap_put, hashmap_slots_for, HashMapMap,
+    DEFAULT_MAX_LOAD_FACTOR,
+};
+
+impl<K, V, S> Serialize for HashMapMap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serializer.collect_map(hashmap_iter(self))
+    }
+}
+
+// Most entries the table is sized for up front. The size hint comes from the
+// input, so a malformed or hostile length prefix must not be able to demand
+// a huge allocation; past this the table grows as entries actually arrive.
+const MAX_PREALLOCATED_ENTRIES: usize = 4096;
+
+struct MapVisitor<K, V, S> {
+    marker: PhantomData<HashMapMap<K, V, S>>,
+}
+
+impl<'de, K, V, S> Visitor<'de> for MapVisitor<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    type Value = HashMapMap<K, V, S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let lower = access
+            .size_hint()
+            .unwrap_or(0)
+            .min(MAX_PREALLOCATED_ENTRIES);
+        let mut m = hashmap_alloc(
+            hashmap_slots_for(lower, DEFAULT_MAX_LOAD_FACTOR),
+            S::default(),
+        );
+        while let Some((key, value)) = access.next_entry()? {
+            hashmap_put(&mut m, key, value).map_err(serde::de::Error::custom)?;
+        }
+
+        Ok(m)
+    }
+}
+
+impl<'de, K, V, S> Deserialize<'de> for HashMapMap<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use crate::{hashmap_new, hashmap_put, hashmap_validate, MapT};
+
+    #[test]
+    fn json_round_trip_gives_an_equal_map() {
+        let mut m = hashmap_new::<String, u32>();
+        for k in 0..100 {
+            hashmap_put(&mut m, k.to_string(), k).unwrap();
+        }
+        let json = serde_json::to_string(&m).unwrap();
+        assert!(!json.contains("in_use"));
+        let back: MapT<String, u32> = serde_json::from_str(&json).unwrap();
+        hashmap_validate(&back).unwrap();
+        assert_eq!(back, m);
+    }
+
+    #[test]
+    fn huge_length_prefix_does_not_preallocate() {
+        let input = (1u64 << 40).to_le_bytes();
+        assert!(bincode::deserialize::<MapT<u32, u32>>(&input).is_err());
+    }
+}
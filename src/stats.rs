@@ -0,0 +1,70 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Operation counters for a map, returned by `hashmap_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapStats {
+    /// Calls to `hashmap_put` plus keys inserted through `Entry`.
+    pub inserts: usize,
+    /// Key lookups, including the ones done by removals.
+    pub lookups: usize,
+    /// Slots inspected across all lookups.
+    pub probes: usize,
+    /// Times the table was rebuilt at a new size, growing or shrinking.
+    pub rehashes: usize,
+}
+
+impl MapStats {
+    /// Mean number of slots a lookup inspects, or 0 before any lookup.
+    pub fn average_probes(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.probes as f64 / self.lookups as f64
+    }
+}
+
+// Live counters kept inside the map. Lookups only borrow the map, so they
+// are atomics rather than Cells, which would make the map !Sync.
+#[derive(Default)]
+pub(crate) struct Counters {
+    inserts: AtomicUsize,
+    lookups: AtomicUsize,
+    probes: AtomicUsize,
+    rehashes: AtomicUsize,
+}
+
+impl Counters {
+    pub(crate) fn record_insert(&self) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_lookup(&self, probes: usize) {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        self.probes.fetch_add(probes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_rehash(&self) {
+        self.rehashes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> MapStats {
+        MapStats {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            lookups: self.lookups.load(Ordering::Relaxed),
+            probes: self.probes.load(Ordering::Relaxed),
+            rehashes: self.rehashes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        let stats = self.snapshot();
+        Counters {
+            inserts: AtomicUsize::new(stats.inserts),
+            lookups: AtomicUsize::new(stats.lookups),
+            probes: AtomicUsize::new(stats.probes),
+            rehashes: AtomicUsize::new(stats.rehashes),
+        }
+    }
+}